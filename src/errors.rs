@@ -1,9 +1,89 @@
-use light_arrangements::LightArrangementError;
-use pyo3::{exceptions::PyValueError, prelude::*};
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*};
 
-pub fn to_pyresult<T>(result: Result<T, LightArrangementError>) -> Result<T, PyErr> {
-    match result {
-        Ok(t) => Ok(t),
-        Err(e) => Err(PyValueError::new_err(e.reason())),
-    }
+create_exception!(
+    light_arrangements_python,
+    LightArrangementError,
+    PyValueError,
+    "Base class for every error raised by light arrangements"
+);
+create_exception!(
+    light_arrangements_python,
+    ArrangementInitError,
+    LightArrangementError,
+    "Raised when a light arrangement could not be created"
+);
+create_exception!(
+    light_arrangements_python,
+    IndexOutOfRangeError,
+    LightArrangementError,
+    "Raised when a light index is past the end of the strip"
+);
+create_exception!(
+    light_arrangements_python,
+    DimensionMismatchError,
+    LightArrangementError,
+    "Raised when input doesn't match the number of dimensions of the arrangement"
+);
+create_exception!(
+    light_arrangements_python,
+    InvalidColorError,
+    LightArrangementError,
+    "Raised when a color can't be understood, such as a sequence without exactly 3 values"
+);
+create_exception!(
+    light_arrangements_python,
+    WorkerThreadError,
+    LightArrangementError,
+    "Raised when the thread holding the light arrangement can't be reached or misbehaves"
+);
+create_exception!(
+    light_arrangements_python,
+    ResponseTimeoutError,
+    WorkerThreadError,
+    "Raised when the thread holding the light arrangement takes longer than the timeout to respond"
+);
+create_exception!(
+    light_arrangements_python,
+    ArrangementClosedError,
+    LightArrangementError,
+    "Raised when a light arrangement is used after it was closed"
+);
+
+/// Error raised when the light arrangement thread fails to start, with the reason it gave
+pub fn init_error(reason: String) -> PyErr {
+    ArrangementInitError::new_err(format!(
+        "Failed to start light arrangement thread: {}",
+        reason
+    ))
+}
+
+/// Adds every exception class to the python module
+pub fn add_exceptions_to_module(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add(
+        "LightArrangementError",
+        py.get_type::<LightArrangementError>(),
+    )?;
+    m.add(
+        "ArrangementInitError",
+        py.get_type::<ArrangementInitError>(),
+    )?;
+    m.add(
+        "IndexOutOfRangeError",
+        py.get_type::<IndexOutOfRangeError>(),
+    )?;
+    m.add(
+        "DimensionMismatchError",
+        py.get_type::<DimensionMismatchError>(),
+    )?;
+    m.add("InvalidColorError", py.get_type::<InvalidColorError>())?;
+    m.add("WorkerThreadError", py.get_type::<WorkerThreadError>())?;
+    m.add(
+        "ResponseTimeoutError",
+        py.get_type::<ResponseTimeoutError>(),
+    )?;
+    m.add(
+        "ArrangementClosedError",
+        py.get_type::<ArrangementClosedError>(),
+    )?;
+    Ok(())
 }
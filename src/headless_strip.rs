@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex};
+
+use light_arrangements::{Color, LightStrip};
+
+use crate::rgbw_strip::WhiteChannel;
+
+/// Red, green, blue and white values of every light a headless strip last showed, shared so they
+/// can be read from outside the light arrangement thread
+pub type ShownFrame = Arc<Mutex<Vec<[u8; 4]>>>;
+
+/// A light strip that only stores its colors in memory. It has no window and drives no hardware,
+/// so it can be used on machines without a display or GPIO, such as CI servers
+pub struct HeadlessStrip {
+    colors: Vec<Color>,
+    /// White values to show on RGBW strips
+    white: Option<WhiteChannel>,
+    /// Where each shown frame is copied to
+    shown: ShownFrame,
+}
+
+impl HeadlessStrip {
+    /// Creates a strip of `number_lights` lights, all initially off, copying every frame it shows
+    /// into `shown` along with the white values in `white`
+    pub fn new(number_lights: usize, white: Option<WhiteChannel>, shown: ShownFrame) -> Self {
+        *shown.lock().unwrap() = vec![[0; 4]; number_lights];
+        Self {
+            colors: vec![
+                Color {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                };
+                number_lights
+            ],
+            white,
+            shown,
+        }
+    }
+}
+
+impl LightStrip for HeadlessStrip {
+    fn get(&self, index: usize) -> Color {
+        self.colors[index].clone()
+    }
+
+    fn set(&mut self, index: usize, color: &Color) {
+        self.colors[index] = color.clone();
+    }
+
+    fn fill(&mut self, color: &Color) {
+        for light in self.colors.iter_mut() {
+            *light = color.clone();
+        }
+    }
+
+    fn show(&mut self) {
+        let white = self.white.as_ref().map(|white| white.borrow());
+        let mut shown = self.shown.lock().unwrap();
+        for (index, color) in self.colors.iter().enumerate() {
+            let white = white.as_ref().map_or(0, |white| white[index]);
+            shown[index] = [color.red, color.green, color.blue, white];
+        }
+    }
+}
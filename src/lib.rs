@@ -1,18 +1,28 @@
-use light_arrangement_python_obj::PyLightArrangement;
+use light_arrangement_python_obj::{PyFrame, PyLightArrangement};
 use pyo3::prelude::*;
 use pyo3::{types::PyModule, Python};
 
 mod errors;
+mod headless_strip;
 mod light_arrangement_python_obj;
 mod light_arrangement_thread;
+mod logging;
+mod output_strip;
+mod pycolor;
+mod pyfalloff;
 mod pyloc;
+mod rgbw_strip;
 mod types;
 mod util;
 
+use light_arrangement_python_obj::init_headless;
 use light_arrangement_python_obj::init_test;
 use light_arrangement_python_obj::init_ws281x;
+use light_arrangement_python_obj::stop_all_arrangements;
 
 use light_arrangements::Loc;
+use pycolor::PyColor;
+use pyfalloff::PyFalloff;
 use pyo3::types::PyType;
 use util::vec_to_array;
 
@@ -26,10 +36,22 @@ impl_pyloc_for_dimensions!(
 
 /// A Python module implemented in Rust.
 #[pymodule]
-fn light_arrangements_python(_py: Python, m: &PyModule) -> PyResult<()> {
+fn light_arrangements_python(py: Python, m: &PyModule) -> PyResult<()> {
+    logging::init();
+
+    m.add_function(wrap_pyfunction!(init_headless, m)?)?;
     m.add_function(wrap_pyfunction!(init_test, m)?)?;
     m.add_function(wrap_pyfunction!(init_ws281x, m)?)?;
+    m.add_function(wrap_pyfunction!(logging::reset_logging, m)?)?;
     m.add_class::<PyLightArrangement>()?;
+    m.add_class::<PyFrame>()?;
+    m.add_class::<PyColor>()?;
+    m.add_class::<PyFalloff>()?;
+    errors::add_exceptions_to_module(py, m)?;
+
+    // Lets the lights follow their shutdown policy even if they are never garbage collected
+    py.import("atexit")?
+        .call_method1("register", (wrap_pyfunction!(stop_all_arrangements, m)?,))?;
 
     macro_rules! add_pyloc_methods_to_module {
         ( $($name:ident),* ) => {
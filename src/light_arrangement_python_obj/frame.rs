@@ -0,0 +1,43 @@
+use pyo3::prelude::*;
+use pyo3::types::PyAny;
+
+use super::PyLightArrangement;
+
+/// Context manager that collects every draw made on a light arrangement while it is open, and sends
+/// them to the light arrangement thread together when it closes.
+/// Reads made inside the frame see the lights as they were before the frame started
+#[pyclass(name = "Frame")]
+pub struct PyFrame {
+    arrangement: Py<PyLightArrangement>,
+    show: bool,
+}
+
+impl PyFrame {
+    pub fn new(arrangement: Py<PyLightArrangement>, show: bool) -> Self {
+        Self { arrangement, show }
+    }
+}
+
+#[pymethods]
+impl PyFrame {
+    pub fn __enter__(&self, py: Python) -> PyResult<Py<PyLightArrangement>> {
+        self.arrangement.borrow(py).begin_frame()?;
+        Ok(self.arrangement.clone_ref(py))
+    }
+
+    pub fn __exit__(
+        &self,
+        py: Python,
+        exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<bool> {
+        let arrangement: &PyLightArrangement = &self.arrangement.borrow(py);
+        if exc_type.is_some() {
+            arrangement.discard_frame();
+        } else {
+            py.allow_threads(|| arrangement.end_frame(self.show))?;
+        }
+        Ok(false)
+    }
+}
@@ -5,6 +5,20 @@
 macro_rules! impl_init_test_for_dimensions {
     ( $( ($n:expr, $name:ident) ),* ) => {
         #[pyfunction]
+        #[pyo3(signature = (
+            number_dimensions,
+            input_file,
+            number_children_for_division,
+            sphere_size,
+            camera_start,
+            dimension_mask,
+            *,
+            rgbw=false,
+            max_milliamps=None,
+            volts=5.0
+        ))]
+        // The strip options are keyword only, after the arguments init_test has always taken
+        #[allow(clippy::too_many_arguments)]
         pub fn init_test(
             number_dimensions: usize,
             input_file: String,
@@ -12,6 +26,9 @@ macro_rules! impl_init_test_for_dimensions {
             sphere_size: f32,
             camera_start: (f32, f32, f32),
             dimension_mask: [u8; 3],
+            rgbw: bool,
+            max_milliamps: Option<f64>,
+            volts: f64,
         ) -> PyResult<PyLightArrangement> {
             let test_display_config =
                 TestStripDisplayConfig::new(sphere_size, camera_start, dimension_mask);
@@ -19,13 +36,15 @@ macro_rules! impl_init_test_for_dimensions {
                 $(
                     $n => {
                         let light_arr_threading =
-                            LightArrangementThread::<$n>::test(test_display_config, input_file, number_children_for_division)?;
+                            Python::with_gil(|py| py.allow_threads(|| {
+                                LightArrangementThread::<$n>::test(test_display_config, input_file, number_children_for_division, rgbw, max_milliamps, volts)
+                            }))?;
                         Ok(PyLightArrangement {
                             light_arr_enum: LightArrangementTypes::$name(light_arr_threading),
                         })
                     }
                 )*
-                x => Err(PyValueError::new_err(
+                x => Err(DimensionMismatchError::new_err(
                     format!("Dimension number could not be matched; no branch to handle dimension {}",x),
                 )),
             };
@@ -41,6 +60,21 @@ macro_rules! impl_init_test_for_dimensions {
 macro_rules! impl_init_ws281x_for_dimensions {
     ( $( ($n:expr, $name:ident) ),* ) => {
         #[pyfunction]
+        #[pyo3(signature = (
+            number_dimensions,
+            input_file,
+            number_children_for_division,
+            number_lights,
+            io_pin,
+            brightness,
+            pixel_order,
+            frequency,
+            *,
+            max_milliamps=None,
+            volts=5.0
+        ))]
+        // The strip options are keyword only, after the arguments init_ws281x has always taken
+        #[allow(clippy::too_many_arguments)]
         pub fn init_ws281x(
             number_dimensions: usize,
             input_file: String,
@@ -50,7 +84,33 @@ macro_rules! impl_init_ws281x_for_dimensions {
             brightness: u8,
             pixel_order: String,
             frequency: u32,
+            max_milliamps: Option<f64>,
+            volts: f64,
         ) -> PyResult<PyLightArrangement> {
+            if let Some(rgbw_order) = RgbwOrder::parse(&pixel_order) {
+                // The 4 values of each light are packed into a strip sending 3 values per light
+                let layout = RgbwLayout { number_lights, order: rgbw_order };
+                let packed_number_lights = packed_number_lights(number_lights);
+                let strip_config = LightStripConfig::new(packed_number_lights, io_pin, brightness, ColorOrder::Rgb, frequency);
+
+                return match number_dimensions {
+                    $(
+                        $n => {
+                            let light_arr_threading =
+                                Python::with_gil(|py| py.allow_threads(|| {
+                                    LightArrangementThread::<$n>::new_rgbw::<Ws281xStrip>(strip_config, layout, input_file, number_children_for_division, max_milliamps, volts)
+                                }))?;
+                            Ok(PyLightArrangement {
+                                light_arr_enum: LightArrangementTypes::$name(light_arr_threading),
+                            })
+                        }
+                    )*
+                        x => Err(DimensionMismatchError::new_err(
+                            format!("Dimension number could not be matched; no branch to handle dimension {}",x),
+                        )),
+                };
+            }
+
             let order = match pixel_order.as_str() {
                 "rgb" => Ok(ColorOrder::Rgb),
                 "rbg" => Ok(ColorOrder::Rbg),
@@ -58,9 +118,10 @@ macro_rules! impl_init_ws281x_for_dimensions {
                 "gbr" => Ok(ColorOrder::Gbr),
                 "brg" => Ok(ColorOrder::Brg),
                 "bgr" => Ok(ColorOrder::Bgr),
-                _ => Err(PyValueError::new_err(
+                _ => Err(ArrangementInitError::new_err(
                         format!("Format string \"{}\" doesn't correspond to a color order; should be
-a 3 character string, like \"rgb\" or \"brg\"", pixel_order.as_str()),
+a 3 character string, like \"rgb\" or \"brg\", or a 4 character string for RGBW strips, like
+\"grbw\"", pixel_order.as_str()),
                     ))
             };
             if let Err(e) = order {
@@ -72,18 +133,63 @@ a 3 character string, like \"rgb\" or \"brg\"", pixel_order.as_str()),
             return match number_dimensions {
                 $(
                     $n => {
-                        let light_arr_threading = to_pyresult(
-                            LightArrangementThread::<$n>::new::<Ws281xStrip>(strip_config, input_file, number_children_for_division),
-                        )?;
+                        let light_arr_threading =
+                            Python::with_gil(|py| py.allow_threads(|| {
+                                LightArrangementThread::<$n>::new::<Ws281xStrip>(strip_config, input_file, number_children_for_division, max_milliamps, volts)
+                            }))?;
                         Ok(PyLightArrangement {
                             light_arr_enum: LightArrangementTypes::$name(light_arr_threading),
                         })
                     }
                 )*
-                    x => Err(PyValueError::new_err(
+                    x => Err(DimensionMismatchError::new_err(
                         format!("Dimension number could not be matched; no branch to handle dimension {}",x),
                     )),
             };
         }
     }
 }
+
+/// Given list of (<dimenstion_integer>, <LightArrangementTypes enum name for headless>),
+/// Implements the PyLightArrangement init function for in-memory strips for every
+/// integer dimension
+#[macro_export]
+macro_rules! impl_init_headless_for_dimensions {
+    ( $( ($n:expr, $name:ident) ),* ) => {
+        #[pyfunction]
+        #[pyo3(signature = (
+            number_dimensions,
+            input_file,
+            number_children_for_division,
+            *,
+            rgbw=false,
+            max_milliamps=None,
+            volts=5.0
+        ))]
+        pub fn init_headless(
+            number_dimensions: usize,
+            input_file: String,
+            number_children_for_division: usize,
+            rgbw: bool,
+            max_milliamps: Option<f64>,
+            volts: f64,
+        ) -> PyResult<PyLightArrangement> {
+            return match number_dimensions {
+                $(
+                    $n => {
+                        let light_arr_threading =
+                            Python::with_gil(|py| py.allow_threads(|| {
+                                LightArrangementThread::<$n>::headless(input_file, number_children_for_division, rgbw, max_milliamps, volts)
+                            }))?;
+                        Ok(PyLightArrangement {
+                            light_arr_enum: LightArrangementTypes::$name(light_arr_threading),
+                        })
+                    }
+                )*
+                x => Err(DimensionMismatchError::new_err(
+                    format!("Dimension number could not be matched; no branch to handle dimension {}",x),
+                )),
+            };
+        }
+    };
+}
@@ -8,6 +8,7 @@ macro_rules! impl_methods_for_dimensions {
 
             pub fn get_closest(
                 &self,
+                py: Python,
                 coordinate: Vec<f64>,
                 max_search_distance: f64,
             ) -> PyResult<Option<PythonReturnColor>> {
@@ -15,137 +16,302 @@ macro_rules! impl_methods_for_dimensions {
                     $(
                         LightArrangementTypes::$name(arr) => {
                             let loc = Loc::cartesian(vec_to_array::<$n>(coordinate)?);
-                            let opt_color = arr.get_closest(&loc, max_search_distance)?;
+                            let opt_color = py.allow_threads(|| arr.get_closest(&loc, max_search_distance))?;
                             return Ok(opt_color);
                         }
                     )*
                 }
             }
 
-            pub fn get_by_index(&self, index: usize) -> PyResult<PythonReturnColor> {
+            pub fn get_by_index(&self, py: Python, index: usize) -> PyResult<PythonReturnColor> {
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
-                            let color = arr.get_by_index(index)?;
+                            let color = py.allow_threads(|| arr.get_by_index(index))?;
                             return Ok(color);
                         }
                     )*
                 }
             }
 
+            pub fn get_all(&self, py: Python) -> PyResult<PyObject> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            let frame = py.allow_threads(|| arr.get_all())?;
+                            return frame_to_numpy(py, frame, arr.channels());
+                        }
+                    )*
+                }
+            }
+
+            pub fn positions(&self, py: Python) -> PyResult<PyObject> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            let positions = py.allow_threads(|| arr.positions())?;
+                            return positions_to_numpy(py, positions, $n);
+                        }
+                    )*
+                }
+            }
+
+            pub fn position_of(&self, py: Python, index: usize) -> PyResult<Vec<f64>> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            let position = py.allow_threads(|| arr.position_of(index))?;
+                            return Ok(position);
+                        }
+                    )*
+                }
+            }
+
+            /// Index, position and color of every light inside the box with `loc1` and `loc2` as
+            /// opposite corners, as a list of (index, position, color) tuples
+            pub fn get_all_in_box(
+                &self,
+                py: Python,
+                loc1: Vec<f64>,
+                loc2: Vec<f64>,
+            ) -> PyResult<PythonSelection> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            let loc1 = Loc::cartesian(vec_to_array::<$n>(loc1)?);
+                            let loc2 = Loc::cartesian(vec_to_array::<$n>(loc2)?);
+                            return py.allow_threads(|| arr.get_all_in_box(loc1, loc2));
+                        }
+                    )*
+                }
+            }
+
+            /// Index, position and color of every light within `radius` of `coordinate`, as a
+            /// list of (index, position, color) tuples
+            pub fn get_all_in_radius(
+                &self,
+                py: Python,
+                coordinate: Vec<f64>,
+                radius: f64,
+            ) -> PyResult<PythonSelection> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            let loc = Loc::cartesian(vec_to_array::<$n>(coordinate)?);
+                            return py.allow_threads(|| arr.get_all_in_radius(loc, radius));
+                        }
+                    )*
+                }
+            }
+
+            pub fn indices_in_box(
+                &self,
+                py: Python,
+                loc1: Vec<f64>,
+                loc2: Vec<f64>,
+            ) -> PyResult<Vec<usize>> {
+                let selection = self.get_all_in_box(py, loc1, loc2)?;
+                Ok(selection.into_iter().map(|(index, _, _)| index).collect())
+            }
+
+            pub fn indices_in_radius(
+                &self,
+                py: Python,
+                coordinate: Vec<f64>,
+                radius: f64,
+            ) -> PyResult<Vec<usize>> {
+                let selection = self.get_all_in_radius(py, coordinate, radius)?;
+                Ok(selection.into_iter().map(|(index, _, _)| index).collect())
+            }
+
+            #[pyo3(signature = (coordinate, max_search_distance, color, blend="replace", opacity=1.0))]
             pub fn set_closest(
                 &self,
+                py: Python,
                 coordinate: Vec<f64>,
                 max_search_distance: f64,
-                color: PythonColor,
+                color: &PyAny,
+                blend: &str,
+                opacity: f64,
             ) -> PyResult<()> {
+                let color = to_color(color)?;
+                let blend = to_blend(blend, opacity)?;
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
                             let loc = Loc::cartesian(vec_to_array::<$n>(coordinate)?);
-                            let result = arr.set_closest(&loc, max_search_distance, color)?;
+                            let result = py.allow_threads(|| arr.set_closest(&loc, max_search_distance, color, blend))?;
                             return Ok(result);
                         }
                     )*
                 }
             }
 
+            #[pyo3(signature = (coordinate, set_distance, color, falloff=None))]
             pub fn set_decreasing_intensity(
                 &self,
+                py: Python,
                 coordinate: Vec<f64>,
                 set_distance: f64,
-                color: PythonColor,
+                color: &PyAny,
+                falloff: Option<&PyAny>,
             ) -> PyResult<()> {
+                let color = to_color(color)?;
+                let falloff = to_falloff_arg(falloff)?;
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
                             let loc = Loc::cartesian(vec_to_array::<$n>(coordinate)?);
-                            let result = arr.set_decreasing_intensity(&loc, set_distance, color)?;
+                            let result = py.allow_threads(|| arr.set_decreasing_intensity(&loc, set_distance, color, falloff))?;
                             return Ok(result);
                         }
                     )*
                 }
             }
 
+            #[pyo3(signature = (coordinate, set_distance, color, falloff=None))]
             pub fn set_decreasing_intensity_merge(
                 &self,
+                py: Python,
                 coordinate: Vec<f64>,
                 set_distance: f64,
-                color: PythonColor,
+                color: &PyAny,
+                falloff: Option<&PyAny>,
             ) -> PyResult<()> {
+                let color = to_color(color)?;
+                let falloff = to_falloff_arg(falloff)?;
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
                             let loc = Loc::cartesian(vec_to_array::<$n>(coordinate)?);
-                            let result = arr.set_decreasing_intensity_merge(&loc, set_distance, color)?;
+                            let result = py.allow_threads(|| arr.set_decreasing_intensity_merge(&loc, set_distance, color, falloff))?;
                             return Ok(result);
                         }
                     )*
                 }
             }
 
+            #[pyo3(signature = (loc1, loc2, color, blend="replace", opacity=1.0))]
             pub fn set_all_in_box(
                 &self,
+                py: Python,
                 loc1: Vec<f64>,
                 loc2: Vec<f64>,
-                color: PythonColor,
+                color: &PyAny,
+                blend: &str,
+                opacity: f64,
             ) -> PyResult<()> {
+                let color = to_color(color)?;
+                let blend = to_blend(blend, opacity)?;
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
                             let loc1 = Loc::cartesian(vec_to_array(loc1)?);
                             let loc2 = Loc::cartesian(vec_to_array(loc2)?);
-                            let result = arr.set_all_in_box(loc1, loc2, color)?;
+                            let result = py.allow_threads(|| arr.set_all_in_box(loc1, loc2, color, blend))?;
                             return Ok(result);
                         }
                     )*
                 }
             }
 
+            #[pyo3(signature = (coordinate, radius, color, blend="replace", opacity=1.0))]
             pub fn set_all_in_radius(
                 &self,
+                py: Python,
                 coordinate: Vec<f64>,
                 radius: f64,
-                color: PythonColor,
+                color: &PyAny,
+                blend: &str,
+                opacity: f64,
             ) -> PyResult<()> {
+                let color = to_color(color)?;
+                let blend = to_blend(blend, opacity)?;
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
                             let loc = Loc::cartesian(vec_to_array::<$n>(coordinate)?);
-                            let result = arr.set_all_in_radius(loc, radius, color)?;
+                            let result = py.allow_threads(|| arr.set_all_in_radius(loc, radius, color, blend))?;
                             return Ok(result);
                         }
                     )*
                 }
             }
 
-            pub fn set_by_index(&self, index: usize, color: PythonColor) -> PyResult<()> {
+            #[pyo3(signature = (index, color, blend="replace", opacity=1.0))]
+            pub fn set_by_index(
+                &self,
+                py: Python,
+                index: usize,
+                color: &PyAny,
+                blend: &str,
+                opacity: f64,
+            ) -> PyResult<()> {
+                let color = to_color(color)?;
+                let blend = to_blend(blend, opacity)?;
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
-                            let result = arr.set_by_index(index, color)?;
+                            let result = py.allow_threads(|| arr.set_by_index(index, color, blend))?;
                             return Ok(result);
                         }
                     )*
                 }
             }
 
-            pub fn show(&self) -> PyResult<()> {
+            pub fn set_all(&self, py: Python, colors: PyBuffer<u8>) -> PyResult<()> {
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
-                            let result = arr.show()?;
+                            let frame = buffer_to_frame(py, &colors, arr.number_lights() as usize, arr.channels())?;
+                            let result = py.allow_threads(|| arr.set_all(frame))?;
                             return Ok(result);
                         }
                     )*
                 }
             }
 
-            pub fn fill(&self, color: PythonColor) -> PyResult<()> {
+            pub fn show(&self, py: Python) -> PyResult<()> {
                 match &self.light_arr_enum {
                     $(
                         LightArrangementTypes::$name(arr) => {
-                            let result = arr.fill(color)?;
+                            let result = py.allow_threads(|| arr.show())?;
+                            return Ok(result);
+                        }
+                    )*
+                }
+            }
+
+            pub fn set_async(&self, asynchronous: bool) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.set_asynchronous(asynchronous),
+                    )*
+                }
+            }
+
+            pub fn flush(&self, py: Python) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.flush()),
+                    )*
+                }
+            }
+
+            #[pyo3(signature = (color, blend="replace", opacity=1.0))]
+            pub fn fill(
+                &self,
+                py: Python,
+                color: &PyAny,
+                blend: &str,
+                opacity: f64,
+            ) -> PyResult<()> {
+                let color = to_color(color)?;
+                let blend = to_blend(blend, opacity)?;
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            let result = py.allow_threads(|| arr.fill(color, blend))?;
                             return Ok(result);
                         }
                     )*
@@ -161,6 +327,326 @@ macro_rules! impl_methods_for_dimensions {
                     )*
                 }
             }
+
+            pub fn close(&self, py: Python) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.close()),
+                    )*
+                }
+            }
+
+            pub fn is_alive(&self, py: Python) -> bool {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.is_alive()),
+                    )*
+                }
+            }
+
+            pub fn restart(&self, py: Python) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.restart()),
+                    )*
+                }
+            }
+
+            pub fn set_shutdown_policy(&self, py: Python, policy: &PyAny) -> PyResult<()> {
+                let shutdown_policy = to_shutdown_policy(policy)?;
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.set_shutdown_policy(shutdown_policy))
+                        }
+                    )*
+                }
+            }
+
+            /// Dims the lights when they are shown, from 0 for off to 1 for full brightness
+            pub fn set_brightness(&self, py: Python, brightness: f64) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.set_brightness(brightness))
+                        }
+                    )*
+                }
+            }
+
+            #[getter]
+            pub fn brightness(&self) -> f64 {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.brightness(),
+                    )*
+                }
+            }
+
+            /// Gamma corrects the lights when they are shown, commonly with a gamma of 2.2 to 2.8
+            pub fn set_gamma(&self, py: Python, gamma: f64) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.set_gamma(gamma))
+                        }
+                    )*
+                }
+            }
+
+            /// Gamma the lights are corrected for, or None if lookup tables were set instead
+            #[getter]
+            pub fn gamma(&self) -> Option<f64> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.gamma(),
+                    )*
+                }
+            }
+
+            /// Shows each value as the value at its index in its channel's table, which each
+            /// have 256 values
+            #[pyo3(signature = (red, green, blue, white=None))]
+            pub fn set_lookup_tables(
+                &self,
+                py: Python,
+                red: Vec<u8>,
+                green: Vec<u8>,
+                blue: Vec<u8>,
+                white: Option<Vec<u8>>,
+            ) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.set_lookup_tables(red, green, blue, white))
+                        }
+                    )*
+                }
+            }
+
+            /// Scales the red, green and blue values, each from 0 to 1, when the lights are shown
+            pub fn set_white_balance(
+                &self,
+                py: Python,
+                red: f64,
+                green: f64,
+                blue: f64,
+            ) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.set_white_balance([red, green, blue]))
+                        }
+                    )*
+                }
+            }
+
+            #[getter]
+            pub fn white_balance(&self) -> (f64, f64, f64) {
+                let [red, green, blue] = match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.white_balance(),
+                    )*
+                };
+                (red, green, blue)
+            }
+
+            /// Dims the lights when they are shown so they draw at most `max_milliamps` from a
+            /// supply at `volts`, or removes the limit if `max_milliamps` is None
+            #[pyo3(signature = (max_milliamps, volts=5.0))]
+            pub fn set_power_limit(
+                &self,
+                py: Python,
+                max_milliamps: Option<f64>,
+                volts: f64,
+            ) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.set_power_limit(max_milliamps, volts))
+                        }
+                    )*
+                }
+            }
+
+            #[getter]
+            pub fn max_milliamps(&self) -> Option<f64> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.max_milliamps(),
+                    )*
+                }
+            }
+
+            #[getter]
+            pub fn volts(&self) -> f64 {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.volts(),
+                    )*
+                }
+            }
+
+            /// Estimated power the lights draw once shown, in watts
+            pub fn estimated_power(&self, py: Python) -> PyResult<f64> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.estimated_power())
+                        }
+                    )*
+                }
+            }
+
+            /// Number of values in each color: 4 for RGBW strips, otherwise 3
+            #[getter]
+            pub fn channels(&self) -> usize {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.channels(),
+                    )*
+                }
+            }
+
+            /// Whether colors without a white value sent to RGBW strips have the white they share
+            /// moved into the white channel
+            #[getter]
+            pub fn extract_white(&self) -> bool {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.extract_white(),
+                    )*
+                }
+            }
+
+            #[setter]
+            pub fn set_extract_white(&self, extract_white: bool) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.set_extract_white(extract_white),
+                    )*
+                }
+            }
+
+            #[getter]
+            pub fn timeout(&self, py: Python) -> Option<f64> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.timeout()).map(|timeout| timeout.as_secs_f64())
+                        }
+                    )*
+                }
+            }
+
+            #[setter]
+            pub fn set_timeout(&self, py: Python, seconds: Option<f64>) -> PyResult<()> {
+                let timeout = to_timeout(seconds)?;
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.set_timeout(timeout)),
+                    )*
+                }
+            }
+
+            #[getter]
+            pub fn is_poisoned(&self, py: Python) -> bool {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.is_poisoned()),
+                    )*
+                }
+            }
+
+            /// Message of the last panic on the light arrangement thread, or None if no request
+            /// ever panicked
+            #[getter]
+            pub fn last_worker_error(&self, py: Python) -> Option<String> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.last_worker_error()),
+                    )*
+                }
+            }
+
+            /// Makes the light arrangement thread panic while handling a request. Only in debug
+            /// builds, for testing how panics are reported
+            #[cfg(debug_assertions)]
+            #[pyo3(name = "_panic_worker")]
+            pub fn panic_worker(&self, py: Python) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.panic_worker()),
+                    )*
+                }
+            }
+
+            /// Colors a headless strip last showed, after brightness and color correction, or None
+            /// for other strips. Meant for tests, and still readable once closed
+            pub fn shown_frame(&self, py: Python) -> Option<Vec<PythonReturnColor>> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => {
+                            py.allow_threads(|| arr.shown_frame())
+                        }
+                    )*
+                }
+            }
+
+            #[getter]
+            pub fn is_closed(&self, py: Python) -> bool {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => py.allow_threads(|| arr.is_closed()),
+                    )*
+                }
+            }
+
+            pub fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+                slf
+            }
+
+            pub fn __exit__(
+                &self,
+                py: Python,
+                _exc_type: Option<&PyAny>,
+                _exc_value: Option<&PyAny>,
+                _traceback: Option<&PyAny>,
+            ) -> PyResult<bool> {
+                self.close(py)?;
+                Ok(false)
+            }
+
+            #[pyo3(signature = (show = true))]
+            pub fn frame(slf: PyRef<'_, Self>, show: bool) -> PyFrame {
+                PyFrame::new(slf.into(), show)
+            }
+        }
+
+        impl PyLightArrangement {
+            pub fn begin_frame(&self) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.begin_frame(),
+                    )*
+                }
+            }
+
+            pub fn end_frame(&self, show: bool) -> PyResult<()> {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.end_frame(show),
+                    )*
+                }
+            }
+
+            pub fn discard_frame(&self) {
+                match &self.light_arr_enum {
+                    $(
+                        LightArrangementTypes::$name(arr) => arr.discard_frame(),
+                    )*
+                }
+            }
         }
     };
 }
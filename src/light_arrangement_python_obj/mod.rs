@@ -1,20 +1,31 @@
 use pyo3::prelude::*;
 
-use crate::errors::to_pyresult;
-use crate::light_arrangement_thread::LightArrangementThread;
-use crate::types::PythonColor;
-use crate::{types::PythonReturnColor, util::vec_to_array};
+use crate::errors::{ArrangementInitError, DimensionMismatchError};
+use crate::light_arrangement_thread::{stop_all_workers, LightArrangementThread};
+use crate::rgbw_strip::{packed_number_lights, RgbwLayout, RgbwOrder};
+use crate::{
+    types::{PythonReturnColor, PythonSelection},
+    util::{
+        buffer_to_frame, frame_to_numpy, positions_to_numpy, to_blend, to_color, to_falloff_arg,
+        to_shutdown_policy, to_timeout, vec_to_array,
+    },
+};
 
 use light_arrangements::ColorOrder;
 use light_arrangements::LightStripConfig;
 use light_arrangements::Loc;
 use light_arrangements::TestStripDisplayConfig;
 use light_arrangements::Ws281xStrip;
-use pyo3::exceptions::PyValueError;
+use pyo3::buffer::PyBuffer;
+use pyo3::types::PyAny;
 
+mod frame;
 mod init_macro;
 mod methods_macro;
 
+pub use self::frame::PyFrame;
+
+use crate::impl_init_headless_for_dimensions;
 use crate::impl_init_test_for_dimensions;
 use crate::impl_init_ws281x_for_dimensions;
 use crate::impl_methods_for_dimensions;
@@ -33,10 +44,20 @@ pub enum LightArrangementTypes {
     Ws281x2D(LightArrangementThread<2>),
     Ws281x3D(LightArrangementThread<3>),
     Ws281x4D(LightArrangementThread<4>),
+    Headless1D(LightArrangementThread<1>),
+    Headless2D(LightArrangementThread<2>),
+    Headless3D(LightArrangementThread<3>),
+    Headless4D(LightArrangementThread<4>),
 }
 
 impl_init_test_for_dimensions!((1, Test1D), (2, Test2D), (3, Test3D), (4, Test4D));
 impl_init_ws281x_for_dimensions!((1, Ws281x1D), (2, Ws281x2D), (3, Ws281x3D), (4, Ws281x4D));
+impl_init_headless_for_dimensions!(
+    (1, Headless1D),
+    (2, Headless2D),
+    (3, Headless3D),
+    (4, Headless4D)
+);
 
 impl_methods_for_dimensions!(
     (1, Test1D),
@@ -46,5 +67,16 @@ impl_methods_for_dimensions!(
     (1, Ws281x1D),
     (2, Ws281x2D),
     (3, Ws281x3D),
-    (4, Ws281x4D)
+    (4, Ws281x4D),
+    (1, Headless1D),
+    (2, Headless2D),
+    (3, Headless3D),
+    (4, Headless4D)
 );
+
+/// Stops every light arrangement thread that is still running, so the lights follow their shutdown
+/// policy. Registered with `atexit` when the module is imported
+#[pyfunction]
+pub fn stop_all_arrangements(py: Python) {
+    py.allow_threads(stop_all_workers);
+}
@@ -0,0 +1,83 @@
+use crate::types::PythonColor;
+
+/// How a color being set is combined with the color a light already has
+#[derive(Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    /// Uses the new color
+    Replace,
+    /// Adds the colors, capped at full brightness
+    Add,
+    /// Keeps the brighter of each value
+    Max,
+    /// Keeps the dimmer of each value
+    Min,
+    /// Multiplies the colors as fractions of full brightness, which only ever darkens
+    Multiply,
+    /// Inverse of multiplying the inverted colors, which only ever brightens
+    Screen,
+    /// Uses the new color, drawn over the existing one with the blend's opacity
+    Alpha,
+}
+
+impl BlendMode {
+    /// Parses the name of a blend mode, returning None if there isn't one by that name
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "replace" => Some(Self::Replace),
+            "add" => Some(Self::Add),
+            "max" => Some(Self::Max),
+            "min" => Some(Self::Min),
+            "multiply" => Some(Self::Multiply),
+            "screen" => Some(Self::Screen),
+            "alpha" => Some(Self::Alpha),
+            _ => None,
+        }
+    }
+
+    fn blend_value(&self, existing: u8, new: u8) -> u8 {
+        let (existing, new) = (existing as u32, new as u32);
+        let value = match self {
+            Self::Replace | Self::Alpha => new,
+            Self::Add => (existing + new).min(255),
+            Self::Max => existing.max(new),
+            Self::Min => existing.min(new),
+            Self::Multiply => (existing * new + 127) / 255,
+            Self::Screen => 255 - ((255 - existing) * (255 - new) + 127) / 255,
+        };
+        value as u8
+    }
+}
+
+/// A blend mode along with the opacity it is drawn with, from 0 for leaving the existing color
+/// to 1 for the fully blended one
+#[derive(Clone, Copy)]
+pub struct Blend {
+    pub mode: BlendMode,
+    pub opacity: f64,
+}
+
+impl Blend {
+    /// Overwrites the existing color, which is what setting a color does without a blend
+    pub const REPLACE: Self = Self {
+        mode: BlendMode::Replace,
+        opacity: 1.0,
+    };
+
+    /// Whether the new color is used as it is, so the existing one doesn't need to be read
+    pub fn is_replace(&self) -> bool {
+        matches!(self.mode, BlendMode::Replace | BlendMode::Alpha) && self.opacity >= 1.0
+    }
+
+    /// Combines the values of `new` with the matching values of `existing`
+    pub fn apply(&self, existing: &[u8], new: &[u8]) -> PythonColor {
+        existing
+            .iter()
+            .zip(new.iter())
+            .map(|(&existing, &new)| {
+                let blended = self.mode.blend_value(existing, new) as f64;
+                let existing = existing as f64;
+                (existing + (blended - existing) * self.opacity).round() as u8
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,83 @@
+use std::f64::consts::PI;
+
+/// Shape of the curve the intensity of a color follows as it fades out
+#[derive(Clone, Copy, PartialEq)]
+pub enum FalloffCurve {
+    /// Fades evenly
+    Linear,
+    /// Fades quickly near the center, then slowly
+    Quadratic,
+    /// Fades like light from a point, shifted so it reaches nothing at the edge
+    InverseSquare,
+    /// Fades along a bell curve with the given standard deviation, or a third of the distance it
+    /// fades over if not given
+    Gaussian(Option<f64>),
+    /// Fades slowly at the center and edge, and quickly in between
+    Smoothstep,
+    /// Fades along half a cosine wave, which is close to smoothstep but softer
+    Cosine,
+}
+
+impl FalloffCurve {
+    /// Parses the name of a falloff curve, returning None if there isn't one by that name
+    pub fn parse(curve: &str, sigma: Option<f64>) -> Option<Self> {
+        match curve {
+            "linear" => Some(Self::Linear),
+            "quadratic" => Some(Self::Quadratic),
+            "inverse-square" => Some(Self::InverseSquare),
+            "gaussian" => Some(Self::Gaussian(sigma)),
+            "smoothstep" => Some(Self::Smoothstep),
+            "cosine" => Some(Self::Cosine),
+            _ => None,
+        }
+    }
+
+    /// Name the curve is parsed from
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Quadratic => "quadratic",
+            Self::InverseSquare => "inverse-square",
+            Self::Gaussian(_) => "gaussian",
+            Self::Smoothstep => "smoothstep",
+            Self::Cosine => "cosine",
+        }
+    }
+}
+
+/// How the intensity of a color drops off with the distance from where it is set
+#[derive(Clone, Copy)]
+pub struct Falloff {
+    pub curve: FalloffCurve,
+    /// Distance within which the color is at full intensity
+    pub inner_radius: f64,
+}
+
+impl Falloff {
+    /// Intensity of the color at `distance`, from 1 within the inner radius to 0 at `set_distance`
+    pub fn intensity(&self, distance: f64, set_distance: f64) -> f64 {
+        if distance <= self.inner_radius {
+            return 1.0;
+        }
+        if distance >= set_distance {
+            return 0.0;
+        }
+        let width = set_distance - self.inner_radius;
+        let t = (distance - self.inner_radius) / width;
+        match self.curve {
+            FalloffCurve::Linear => 1.0 - t,
+            FalloffCurve::Quadratic => (1.0 - t).powi(2),
+            FalloffCurve::InverseSquare => {
+                let edge = 1.0 / 16.0;
+                (1.0 / (1.0 + 15.0 * t * t) - edge) / (1.0 - edge)
+            }
+            FalloffCurve::Gaussian(sigma) => {
+                let sigma = sigma.unwrap_or(width / 3.0);
+                let offset = distance - self.inner_radius;
+                (-(offset * offset) / (2.0 * sigma * sigma)).exp()
+            }
+            FalloffCurve::Smoothstep => 1.0 - t * t * (3.0 - 2.0 * t),
+            FalloffCurve::Cosine => (1.0 + (PI * t).cos()) / 2.0,
+        }
+    }
+}
@@ -1,15 +1,37 @@
-use std::sync::mpsc::channel;
-use std::thread;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
 use light_arrangements::{ArrangementConfig, LightStripConfig, TestStrip, TestStripDisplayConfig};
-use light_arrangements::{LightArrangement, LightArrangementError, LightStrip, Loc, RealStrip};
-use pyo3::exceptions::PyValueError;
-use pyo3::PyResult;
+use light_arrangements::{LightArrangement, LightStrip, Loc, RealStrip};
+use log::{debug, error};
+use pyo3::{PyErr, PyResult};
 
-use crate::types::{PythonColor, PythonReturnColor};
+use crate::errors::{
+    init_error, ArrangementClosedError, ArrangementInitError, IndexOutOfRangeError,
+    InvalidColorError, LightArrangementError, ResponseTimeoutError, WorkerThreadError,
+};
+use crate::headless_strip::{HeadlessStrip, ShownFrame};
+use crate::output_strip::{
+    gamma_lookup_tables, identity_lookup_table, OutputSettings, OutputStrip, SharedOutputSettings,
+    StripMode,
+};
+use crate::rgbw_strip::{new_white_channel, PackedRgbwStrip, RgbwLayout, WhiteChannel};
+use crate::types::{PythonColor, PythonFrame, PythonReturnColor, PythonSelection};
+use crate::util::extract_white;
 
+use super::blend::Blend;
+use super::falloff::Falloff;
+use super::light_arrangement_responder::panic_message;
 use super::responses::Responses;
-use super::{requests::Requests, LightArrangementThread};
+use super::shutdown::{ShutdownPolicy, Worker};
+use super::{requests::Envelope, requests::Requests, LightArrangementThread, ResponseState};
+use super::{ConstructionParams, SpawnResult, StripParams};
 
 impl<const N: usize> LightArrangementThread<N> {
     /// Spawns a new thread with the Light Arrangement. This object communicates with the thread to
@@ -18,124 +40,303 @@ impl<const N: usize> LightArrangementThread<N> {
         strip_config: LightStripConfig,
         input_file: String,
         number_children_for_division: usize,
-    ) -> Result<Self, LightArrangementError> {
-        let (request_sender, request_receiver) = channel();
-        let (response_sender, response_receiver) = channel();
-        let number_lights = strip_config.number_lights;
-
-        thread::spawn(move || {
-            let strip_result = T::new(strip_config);
-            if let Err(error) = strip_result {
-                eprintln!("Failed to create light strip: {}", error.reason());
-                return;
-            }
-
-            let arrangement_config_result =
-                ArrangementConfig::from_csv(&input_file, number_children_for_division);
-            if let Err(error) = arrangement_config_result {
-                eprintln!("Failed to create arrangement: {}", error.reason());
-                return;
-            }
-
-            let light_arrangement_res =
-                LightArrangement::new(strip_result.unwrap(), arrangement_config_result.unwrap());
-            if let Ok(light_arrangement) = light_arrangement_res {
-                Self::light_arrangement_thread(
-                    light_arrangement,
-                    request_receiver,
-                    response_sender,
-                );
-            } else {
-                eprintln!(
-                    "Failed to create light arrangment: {}",
-                    light_arrangement_res.err().unwrap().reason()
-                );
-            }
-        });
+        max_milliamps: Option<f64>,
+        volts: f64,
+    ) -> PyResult<Self> {
+        let params = ConstructionParams {
+            strip: StripParams::Real(strip_config, None, Self::spawn_real::<T>),
+            input_file,
+            number_children_for_division,
+            rgbw: false,
+        };
+        Self::start(params, max_milliamps, volts)
+    }
 
-        return Ok(Self {
-            request_sender,
-            response_receiver,
-            number_lights,
-        });
+    /// Spawns a new thread with the Light Arrangement for an RGBW strip, driven through a strip of
+    /// type `T` configured by `strip_config` with `packed_number_lights` lights
+    /// in "rgb" order
+    pub fn new_rgbw<T: LightStrip + RealStrip>(
+        strip_config: LightStripConfig,
+        layout: RgbwLayout,
+        input_file: String,
+        number_children_for_division: usize,
+        max_milliamps: Option<f64>,
+        volts: f64,
+    ) -> PyResult<Self> {
+        let params = ConstructionParams {
+            strip: StripParams::Real(strip_config, Some(layout), Self::spawn_real::<T>),
+            input_file,
+            number_children_for_division,
+            rgbw: true,
+        };
+        Self::start(params, max_milliamps, volts)
     }
 
-    /// Spawns a new thread with a Test Strip, returning the object to control it
+    /// Spawns a new thread with a Test Strip, returning the object to control it. If `rgbw` is
+    /// set the thread also keeps a white value for every light, though the display only shows the
+    /// red, green and blue values
     pub fn test(
         test_strip_config: TestStripDisplayConfig,
         input_file: String,
         number_children_for_division: usize,
+        rgbw: bool,
+        max_milliamps: Option<f64>,
+        volts: f64,
     ) -> PyResult<Self> {
-        let (request_sender, request_receiver) = channel();
-        let (response_sender, response_receiver) = channel();
+        let params = ConstructionParams {
+            strip: StripParams::Test(test_strip_config),
+            input_file,
+            number_children_for_division,
+            rgbw,
+        };
+        Self::start(params, max_milliamps, volts)
+    }
 
-        let arrangement_config_result =
-            ArrangementConfig::from_csv(&input_file, number_children_for_division);
-        if let Err(_) = arrangement_config_result {
-            return Err(PyValueError::new_err(
-                "Failed to create arrangement from csv file",
-            ));
+    /// Spawns a new thread with a strip that only stores its colors in memory, returning the
+    /// object to control it. Needs no display or GPIO. If `rgbw` is set it also stores a white
+    /// value for every light
+    pub fn headless(
+        input_file: String,
+        number_children_for_division: usize,
+        rgbw: bool,
+        max_milliamps: Option<f64>,
+        volts: f64,
+    ) -> PyResult<Self> {
+        let params = ConstructionParams {
+            strip: StripParams::Headless(ShownFrame::default()),
+            input_file,
+            number_children_for_division,
+            rgbw,
+        };
+        Self::start(params, max_milliamps, volts)
+    }
+
+    /// Spawns the light arrangement thread described by `params`, limited to drawing
+    /// `max_milliamps` at `volts`, returning the object to control it. The power limit is checked
+    /// before anything is spawned, so a bad one never sets up the strip's hardware
+    fn start(
+        params: ConstructionParams<N>,
+        max_milliamps: Option<f64>,
+        volts: f64,
+    ) -> PyResult<Self> {
+        check_power_limit(max_milliamps, volts)?;
+        let output_settings = OutputSettings {
+            max_milliamps,
+            volts,
+            // ws281x strips dim the lights by their own brightness, so they draw less current
+            strip_brightness: match &params.strip {
+                StripParams::Real(strip_config, ..) => strip_config.brightness,
+                _ => u8::MAX,
+            },
+            ..OutputSettings::default()
+        };
+
+        let (worker, number_lights) = Self::spawn(&params)?;
+        let arrangement = Self {
+            worker: RwLock::new(worker),
+            params,
+            shutdown_policy: Mutex::new(ShutdownPolicy::Blank),
+            output_settings: Mutex::new(output_settings.clone()),
+            responses: Mutex::new(ResponseState {
+                unread: Vec::new(),
+                errors: Vec::new(),
+                timeout: None,
+                last_worker_error: None,
+            }),
+            number_lights: AtomicI32::new(number_lights),
+            batches: Mutex::new(HashMap::new()),
+            asynchronous: AtomicBool::new(false),
+            extract_white: AtomicBool::new(false),
+        };
+
+        arrangement.request_none(Requests::SetOutputSettings(output_settings))?;
+        Ok(arrangement)
+    }
+
+    /// Spawns a light arrangement thread from `params`, returning it along with its number of
+    /// lights once it has started
+    fn spawn(params: &ConstructionParams<N>) -> SpawnResult<N> {
+        let input_file = params.input_file.clone();
+        let number_children_for_division = params.number_children_for_division;
+        match &params.strip {
+            StripParams::Real(strip_config, layout, spawn_real) => spawn_real(
+                strip_config.clone(),
+                *layout,
+                input_file,
+                number_children_for_division,
+            ),
+            StripParams::Test(test_strip_config) => {
+                let test_strip_config = test_strip_config.clone();
+                Self::spawn_local(
+                    input_file,
+                    number_children_for_division,
+                    params.rgbw,
+                    move |arrangement_config| {
+                        (TestStrip::new(arrangement_config, &test_strip_config), None)
+                    },
+                )
+            }
+            StripParams::Headless(shown) => {
+                let shown = shown.clone();
+                let rgbw = params.rgbw;
+                Self::spawn_local(
+                    input_file,
+                    number_children_for_division,
+                    rgbw,
+                    move |arrangement_config| {
+                        let number_lights = arrangement_config.light_locations.len();
+                        let output_white = rgbw.then(|| new_white_channel(number_lights));
+                        let strip = HeadlessStrip::new(number_lights, output_white.clone(), shown);
+                        (strip, output_white)
+                    },
+                )
+            }
         }
+    }
 
-        let number_lights = arrangement_config_result
-            .as_ref()
-            .unwrap()
-            .light_locations
-            .len() as i32;
-
-        thread::spawn(move || {
-            // Move the arrangement into the new thread
-            let arrangement_config_result = arrangement_config_result;
-
-            let test_strip = TestStrip::new(
-                &arrangement_config_result.as_ref().unwrap(),
-                &test_strip_config,
-            );
-
-            let light_arrangement_res =
-                LightArrangement::new(test_strip, arrangement_config_result.unwrap());
-
-            if let Ok(light_arrangement) = light_arrangement_res {
-                if response_sender.send(Responses::InitOk).is_err() {
-                    eprintln!(
-                        "Failed to send back to main thread that light arrangmeent succesfully
-                started"
+    fn spawn_real<T: LightStrip + RealStrip>(
+        strip_config: LightStripConfig,
+        layout: Option<RgbwLayout>,
+        input_file: String,
+        number_children_for_division: usize,
+    ) -> SpawnResult<N> {
+        let (request_sender, request_receiver) = channel();
+        let (init_sender, init_receiver) = channel();
+        let number_lights = match layout {
+            Some(layout) => layout.number_lights,
+            None => strip_config.number_lights,
+        };
+
+        let worker = thread::spawn(move || {
+            let strip = match T::new(strip_config) {
+                Ok(strip) => strip,
+                Err(error) => {
+                    send_init_failed(
+                        &init_sender,
+                        format!("Failed to create light strip: {}", error.reason()),
                     );
+                    return;
                 }
-                Self::light_arrangement_thread(light_arrangement, request_receiver, response_sender)
-            } else {
-                eprintln!(
-                    "Failed to create light arrangment: {}",
-                    light_arrangement_res.err().unwrap().reason()
-                );
-                if response_sender.send(Responses::InitFailed).is_err() {
-                    eprintln!(
-                        "Failed to send back to main thread that light arrangement thread
-                failed to start"
-                    );
+            };
+
+            let arrangement_config =
+                match ArrangementConfig::from_csv(&input_file, number_children_for_division) {
+                    Ok(arrangement_config) => arrangement_config,
+                    Err(error) => {
+                        send_init_failed(
+                            &init_sender,
+                            format!("Failed to create arrangement: {}", error.reason()),
+                        );
+                        return;
+                    }
+                };
+
+            match layout {
+                None => Self::run_arrangement(
+                    strip,
+                    arrangement_config,
+                    false,
+                    None,
+                    &init_sender,
+                    request_receiver,
+                ),
+                Some(layout) => {
+                    let output_white = new_white_channel(layout.number_lights as usize);
+                    let strip = PackedRgbwStrip::new(strip, output_white.clone(), layout.order);
+                    Self::run_arrangement(
+                        strip,
+                        arrangement_config,
+                        true,
+                        Some(output_white),
+                        &init_sender,
+                        request_receiver,
+                    )
                 }
-                return;
             }
         });
 
-        let init_response = response_receiver.recv();
-        return match init_response {
-            Ok(Responses::InitOk) => Ok(Self {
-                request_sender,
-                response_receiver,
-                number_lights,
-            }),
-            Ok(Responses::InitFailed) => Err(PyValueError::new_err(
-                "Failed to start light arrangement thread!",
-            )),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Failed to start light arrangement thread; Internally returned wrong response",
-            )),
-            _ => Err(PyValueError::new_err(
-                "Failed to start light thread due to receive error",
-            )),
-        };
+        Self::wait_for_init(request_sender, init_receiver, worker, number_lights)
+    }
+
+    /// Spawns a light arrangement thread for the arrangement in `input_file`, driving a strip made
+    /// on that thread by `new_strip`, for strips that can't be moved between threads. `new_strip`
+    /// also gives the channel the strip shows white values from, if it shows them
+    fn spawn_local<S: LightStrip>(
+        input_file: String,
+        number_children_for_division: usize,
+        rgbw: bool,
+        new_strip: impl FnOnce(&ArrangementConfig<N>) -> (S, Option<WhiteChannel>) + Send + 'static,
+    ) -> SpawnResult<N> {
+        let (request_sender, request_receiver) = channel();
+        let (init_sender, init_receiver) = channel();
+
+        let arrangement_config =
+            ArrangementConfig::from_csv(&input_file, number_children_for_division).map_err(
+                |error| {
+                    ArrangementInitError::new_err(format!(
+                        "Failed to create arrangement from csv file: {}",
+                        error.reason()
+                    ))
+                },
+            )?;
+        let number_lights = arrangement_config.light_locations.len() as i32;
+
+        let worker = thread::spawn(move || {
+            let (strip, output_white) = new_strip(&arrangement_config);
+            Self::run_arrangement(
+                strip,
+                arrangement_config,
+                rgbw,
+                output_white,
+                &init_sender,
+                request_receiver,
+            )
+        });
+
+        Self::wait_for_init(request_sender, init_receiver, worker, number_lights)
+    }
+
+    /// Creates the light arrangement on the light arrangement thread, then handles requests until
+    /// told to quit. `rgbw` is whether the lights have a white channel, and `output_white` holds
+    /// the white values shown by strips that can show them
+    fn run_arrangement<T: LightStrip>(
+        strip: T,
+        arrangement_config: ArrangementConfig<N>,
+        rgbw: bool,
+        output_white: Option<WhiteChannel>,
+        init_sender: &Sender<Responses>,
+        request_receiver: Receiver<Envelope<N>>,
+    ) {
+        let light_locations = arrangement_config.light_locations.clone();
+        let number_lights = light_locations.len();
+        let white = rgbw.then(|| new_white_channel(number_lights));
+        let output_settings = SharedOutputSettings::default();
+        let strip_mode = Rc::new(RefCell::new(StripMode::Colors));
+        let strip = OutputStrip::new(
+            strip,
+            number_lights,
+            output_settings.clone(),
+            white.clone(),
+            output_white,
+            strip_mode.clone(),
+        );
+        match LightArrangement::new(strip, arrangement_config) {
+            Ok(light_arrangement) => {
+                send_init_ok(init_sender);
+                Self::light_arrangement_thread(
+                    light_arrangement,
+                    light_locations,
+                    white,
+                    strip_mode,
+                    output_settings,
+                    request_receiver,
+                )
+            }
+            Err(error) => send_init_failed(
+                init_sender,
+                format!("Failed to create light arrangment: {}", error.reason()),
+            ),
+        }
     }
 
     pub fn get_closest(
@@ -143,34 +344,54 @@ impl<const N: usize> LightArrangementThread<N> {
         loc: &Loc<N>,
         max_search_distance: f64,
     ) -> PyResult<Option<PythonReturnColor>> {
-        let send_result = self
-            .request_sender
-            .send(Requests::GetClosest(loc.clone(), max_search_distance));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+        match self.request(Requests::GetClosest(loc.clone(), max_search_distance))? {
+            Responses::OptionColorResponse(c) => Ok(c),
+            _ => Err(wrong_response_error()),
         }
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::OptionColorResponse(c)) => Ok(c),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            _ => Err(PyValueError::new_err(
-                "Got wrong response internally from Light Arrangement thread",
-            )),
+    /// Index, position and color of every light inside the box with `loc1` and `loc2` as
+    /// opposite corners
+    pub fn get_all_in_box(&self, loc1: Loc<N>, loc2: Loc<N>) -> PyResult<PythonSelection> {
+        match self.request(Requests::GetBox(loc1, loc2))? {
+            Responses::SelectionResponse(selection) => Ok(selection),
+            _ => Err(wrong_response_error()),
+        }
+    }
+
+    /// Index, position and color of every light within `radius` of `loc`
+    pub fn get_all_in_radius(&self, loc: Loc<N>, radius: f64) -> PyResult<PythonSelection> {
+        match self.request(Requests::GetRadius(loc, radius))? {
+            Responses::SelectionResponse(selection) => Ok(selection),
+            _ => Err(wrong_response_error()),
         }
     }
 
     pub fn get_by_index(&self, index: usize) -> PyResult<PythonReturnColor> {
-        let send_result = self.request_sender.send(Requests::GetByIndex(index));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+        match self.request(Requests::GetByIndex(index))? {
+            Responses::ColorResponse(c) => Ok(c),
+            _ => Err(wrong_response_error()),
         }
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::ColorResponse(c)) => Ok(c),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            _ => Err(PyValueError::new_err(
-                "Got wrong response internally from Light Arrangement thread",
-            )),
+    pub fn get_all(&self) -> PyResult<PythonFrame> {
+        match self.request(Requests::GetAll)? {
+            Responses::FrameResponse(colors) => Ok(colors),
+            _ => Err(wrong_response_error()),
+        }
+    }
+
+    pub fn positions(&self) -> PyResult<Vec<f64>> {
+        match self.request(Requests::GetPositions)? {
+            Responses::PositionsResponse(positions) => Ok(positions),
+            _ => Err(wrong_response_error()),
+        }
+    }
+
+    pub fn position_of(&self, index: usize) -> PyResult<Vec<f64>> {
+        match self.request(Requests::GetPosition(index))? {
+            Responses::PositionResponse(position) => Ok(position),
+            _ => Err(wrong_response_error()),
         }
     }
 
@@ -179,28 +400,15 @@ impl<const N: usize> LightArrangementThread<N> {
         loc: &Loc<N>,
         max_search_distance: f64,
         color: PythonColor,
+        blend: Blend,
     ) -> PyResult<()> {
-        let send_result = self.request_sender.send(Requests::SetClosest(
+        let color = self.color_for_strip(color)?;
+        self.submit(Requests::SetClosest(
             loc.clone(),
             max_search_distance,
             color,
-        ));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
-        }
-
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Expected None response internally but got value",
-            )),
-            Err(_) => {
-                return Err(PyValueError::new_err(
-                    "Failed to receive response from Light Arrangement thread",
-                ));
-            }
-        }
+            blend,
+        ))
     }
 
     pub fn set_decreasing_intensity(
@@ -208,28 +416,15 @@ impl<const N: usize> LightArrangementThread<N> {
         loc: &Loc<N>,
         set_distance: f64,
         color: PythonColor,
+        falloff: Option<Falloff>,
     ) -> PyResult<()> {
-        let send_result = self.request_sender.send(Requests::SetDecreasingIntensity(
+        let color = self.color_for_strip(color)?;
+        self.submit(Requests::SetDecreasingIntensity(
             loc.clone(),
             set_distance,
             color,
-        ));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
-        }
-
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Expected None response internally but got value",
-            )),
-            Err(_) => {
-                return Err(PyValueError::new_err(
-                    "Failed to receive response from Light Arrangement thread",
-                ));
-            }
-        }
+            falloff,
+        ))
     }
 
     pub fn set_decreasing_intensity_merge(
@@ -237,133 +432,635 @@ impl<const N: usize> LightArrangementThread<N> {
         loc: &Loc<N>,
         set_distance: f64,
         color: PythonColor,
+        falloff: Option<Falloff>,
     ) -> PyResult<()> {
-        let send_result = self
-            .request_sender
-            .send(Requests::SetDecreasingIntensityMerge(
-                loc.clone(),
-                set_distance,
-                color,
-            ));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+        let color = self.color_for_strip(color)?;
+        self.submit(Requests::SetDecreasingIntensityMerge(
+            loc.clone(),
+            set_distance,
+            color,
+            falloff,
+        ))
+    }
+
+    pub fn set_all_in_box(
+        &self,
+        loc1: Loc<N>,
+        loc2: Loc<N>,
+        color: PythonColor,
+        blend: Blend,
+    ) -> PyResult<()> {
+        let color = self.color_for_strip(color)?;
+        self.submit(Requests::SetBox(loc1, loc2, color, blend))
+    }
+
+    pub fn set_all_in_radius(
+        &self,
+        loc: Loc<N>,
+        radius: f64,
+        color: PythonColor,
+        blend: Blend,
+    ) -> PyResult<()> {
+        let color = self.color_for_strip(color)?;
+        self.submit(Requests::SetRadius(loc, radius, color, blend))
+    }
+
+    pub fn set_by_index(&self, index: usize, color: PythonColor, blend: Blend) -> PyResult<()> {
+        let color = self.color_for_strip(color)?;
+        self.submit(Requests::SetByIndex(index, color, blend))
+    }
+
+    pub fn set_all(&self, colors: PythonFrame) -> PyResult<()> {
+        self.submit(Requests::SetAll(colors))
+    }
+
+    pub fn fill(&self, color: PythonColor, blend: Blend) -> PyResult<()> {
+        let color = self.color_for_strip(color)?;
+        self.submit(Requests::Fill(color, blend))
+    }
+
+    /// Shows the lights, waiting for it along with every asynchronous draw request before it so
+    /// their errors are raised here
+    pub fn show(&self) -> PyResult<()> {
+        self.submit(Requests::Show)?;
+        self.flush()
+    }
+
+    /// Sets whether draw requests are sent without waiting for the light arrangement thread to
+    /// finish them. Errors from those requests are raised on the next `show` or `flush`
+    pub fn set_asynchronous(&self, asynchronous: bool) -> PyResult<()> {
+        self.check_open()?;
+        self.asynchronous.store(asynchronous, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Waits for every asynchronous draw request to finish, raising the first error from them
+    pub fn flush(&self) -> PyResult<()> {
+        let mut responses = self.responses.lock().unwrap();
+        self.check_usable()?;
+        read_unread_responses(&self.worker(), &mut responses, true)?;
+        raise_async_error(&mut responses)
+    }
+
+    pub fn number_lights(&self) -> i32 {
+        self.number_lights.load(Ordering::SeqCst)
+    }
+
+    /// Number of values in each color: 4 for RGBW strips, otherwise 3
+    pub fn channels(&self) -> usize {
+        match self.params.rgbw {
+            true => 4,
+            false => 3,
         }
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Expected None response internally but got value",
+    /// Sets whether colors without a white value are converted to RGBW by moving the white they
+    /// share into the white channel, instead of leaving the white channel off
+    pub fn set_extract_white(&self, extract_white: bool) -> PyResult<()> {
+        self.check_open()?;
+        self.extract_white.store(extract_white, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn extract_white(&self) -> bool {
+        self.extract_white.load(Ordering::SeqCst)
+    }
+
+    /// Sets how long to wait for each response from the light arrangement thread before raising an
+    /// error, or to wait forever if `timeout` is None
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> PyResult<()> {
+        self.check_open()?;
+        self.responses.lock().unwrap().timeout = timeout;
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.responses.lock().unwrap().timeout
+    }
+
+    /// Message of the last panic on the light arrangement thread, if a request ever panicked
+    pub fn last_worker_error(&self) -> Option<String> {
+        self.responses.lock().unwrap().last_worker_error.clone()
+    }
+
+    /// Whether a response timed out, so no more requests can be made
+    pub fn is_poisoned(&self) -> bool {
+        self.worker().is_poisoned()
+    }
+
+    /// Stops the light arrangement thread and waits for it to exit. Every call made afterwards
+    /// raises an error
+    pub fn close(&self) -> PyResult<()> {
+        // No lock is held while waiting for the thread, since it needs the GIL to log, which
+        // callers waiting on the locks may hold
+        match self.worker().stop_unless_poisoned(None) {
+            Some(false) => Err(WorkerThreadError::new_err(
+                "Light Arrangement thread panicked before it was closed",
             )),
-            Err(_) => {
-                return Err(PyValueError::new_err(
-                    "Failed to receive response from Light Arrangement thread",
-                ));
-            }
+            _ => Ok(()),
         }
     }
 
-    pub fn set_all_in_box(&self, loc1: Loc<N>, loc2: Loc<N>, color: PythonColor) -> PyResult<()> {
-        let send_result =
-            self.request_sender
-                .send(Requests::SetBox(loc1.clone(), loc2.clone(), color));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+    pub fn is_closed(&self) -> bool {
+        self.worker().is_closed()
+    }
+
+    /// Whether the light arrangement thread is running and able to answer requests
+    pub fn is_alive(&self) -> bool {
+        let worker = self.worker();
+        !worker.is_poisoned() && !worker.is_closed() && worker.is_running()
+    }
+
+    /// Stops the light arrangement thread if it is still running, then builds a new one from the
+    /// parameters this arrangement was created with. The new thread starts with every light off.
+    /// A closed arrangement stays closed
+    pub fn restart(&self) -> PyResult<()> {
+        self.check_open()?;
+        // Like `close`, no lock is held while the old thread stops or the new one starts. A thread
+        // that panicked has already stopped, which is why it is being restarted
+        self.worker().stop_unless_poisoned(None);
+
+        let (new_worker, number_lights) = Self::spawn(&self.params)?;
+        let mut responses = self.responses.lock().unwrap();
+        *self.worker.write().unwrap() = new_worker;
+        self.number_lights.store(number_lights, Ordering::SeqCst);
+        responses.unread.clear();
+        responses.errors.clear();
+        drop(responses);
+
+        let shutdown_policy = self.shutdown_policy.lock().unwrap().clone();
+        self.request_none(Requests::SetShutdownPolicy(shutdown_policy))?;
+        let output_settings = self.output_settings.lock().unwrap().clone();
+        self.request_none(Requests::SetOutputSettings(output_settings))
+    }
+
+    /// Sets what the lights show once the light arrangement thread stops
+    pub fn set_shutdown_policy(&self, shutdown_policy: ShutdownPolicy) -> PyResult<()> {
+        let shutdown_policy = match shutdown_policy {
+            ShutdownPolicy::Color(color) => ShutdownPolicy::Color(self.color_for_strip(color)?),
+            shutdown_policy => shutdown_policy,
+        };
+        self.request_none(Requests::SetShutdownPolicy(shutdown_policy.clone()))?;
+        *self.shutdown_policy.lock().unwrap() = shutdown_policy;
+        Ok(())
+    }
+
+    /// Sets how bright the lights are shown, from 0 for off to 1 for full brightness
+    pub fn set_brightness(&self, brightness: f64) -> PyResult<()> {
+        if !(0.0..=1.0).contains(&brightness) {
+            return Err(LightArrangementError::new_err(format!(
+                "Brightness must be between 0 and 1, got {}",
+                brightness
+            )));
         }
+        self.update_output_settings(|settings| settings.brightness = brightness)
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Expected None response internally but got value",
-            )),
-            Err(_) => {
-                return Err(PyValueError::new_err(
-                    "Failed to receive response from Light Arrangement thread",
-                ));
-            }
+    pub fn brightness(&self) -> f64 {
+        self.output_settings.lock().unwrap().brightness
+    }
+
+    /// Corrects every value for the gamma of the lights when they are shown, so mid-tones look as
+    /// bright as they should. A gamma of 1 shows values as they are
+    pub fn set_gamma(&self, gamma: f64) -> PyResult<()> {
+        if !gamma.is_finite() || gamma <= 0.0 {
+            return Err(LightArrangementError::new_err(format!(
+                "Gamma must be a positive number, got {}",
+                gamma
+            )));
         }
+        self.update_output_settings(|settings| {
+            settings.lookup_tables = (gamma != 1.0).then(|| gamma_lookup_tables(gamma));
+            settings.gamma = Some(gamma);
+        })
     }
 
-    pub fn set_all_in_radius(&self, loc: Loc<N>, radius: f64, color: PythonColor) -> PyResult<()> {
-        let send_result = self
-            .request_sender
-            .send(Requests::SetRadius(loc.clone(), radius, color));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+    /// Gamma values are corrected for, or None if lookup tables were given directly
+    pub fn gamma(&self) -> Option<f64> {
+        self.output_settings.lock().unwrap().gamma
+    }
+
+    /// Replaces every value of each channel with the value at its index in the channel's table
+    /// when the lights are shown. White values are shown as they are if `white` is not given
+    pub fn set_lookup_tables(
+        &self,
+        red: Vec<u8>,
+        green: Vec<u8>,
+        blue: Vec<u8>,
+        white: Option<Vec<u8>>,
+    ) -> PyResult<()> {
+        let white = match white {
+            Some(white) => to_lookup_table(white)?,
+            None => identity_lookup_table(),
+        };
+        let lookup_tables = Box::new([
+            to_lookup_table(red)?,
+            to_lookup_table(green)?,
+            to_lookup_table(blue)?,
+            white,
+        ]);
+        self.update_output_settings(|settings| {
+            settings.lookup_tables = Some(lookup_tables);
+            settings.gamma = None;
+        })
+    }
+
+    /// Scales the red, green and blue values when the lights are shown, each from 0 to 1, to
+    /// correct the tint of a strip
+    pub fn set_white_balance(&self, white_balance: [f64; 3]) -> PyResult<()> {
+        if white_balance
+            .iter()
+            .any(|scale| !(0.0..=1.0).contains(scale))
+        {
+            return Err(LightArrangementError::new_err(format!(
+                "White balance values must be between 0 and 1, got {:?}",
+                white_balance
+            )));
         }
+        self.update_output_settings(|settings| settings.white_balance = white_balance)
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Expected None response internally but got value",
-            )),
-            Err(_) => {
-                return Err(PyValueError::new_err(
-                    "Failed to receive response from Light Arrangement thread",
-                ));
-            }
+    pub fn white_balance(&self) -> [f64; 3] {
+        self.output_settings.lock().unwrap().white_balance
+    }
+
+    /// Limits the current the lights may draw from a supply at `volts`, dimming them when they
+    /// are shown if they would draw more than `max_milliamps`. No limit is applied if
+    /// `max_milliamps` is None
+    pub fn set_power_limit(&self, max_milliamps: Option<f64>, volts: f64) -> PyResult<()> {
+        check_power_limit(max_milliamps, volts)?;
+        self.update_output_settings(|settings| {
+            settings.max_milliamps = max_milliamps;
+            settings.volts = volts;
+        })
+    }
+
+    pub fn max_milliamps(&self) -> Option<f64> {
+        self.output_settings.lock().unwrap().max_milliamps
+    }
+
+    pub fn volts(&self) -> f64 {
+        self.output_settings.lock().unwrap().volts
+    }
+
+    /// Estimates the power the lights draw once shown, in watts, after they are dimmed to stay
+    /// within the power limit
+    pub fn estimated_power(&self) -> PyResult<f64> {
+        match self.request(Requests::GetEstimatedMilliamps)? {
+            Responses::MilliampsResponse(milliamps) => Ok(milliamps * self.volts() / 1000.0),
+            _ => Err(wrong_response_error()),
         }
     }
 
-    pub fn set_by_index(&self, index: usize, color: PythonColor) -> PyResult<()> {
-        let send_result = self.request_sender.send(Requests::SetByIndex(index, color));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+    /// Makes the light arrangement thread panic while handling a request, so tests can check the
+    /// panic is caught and reported
+    #[cfg(debug_assertions)]
+    pub fn panic_worker(&self) -> PyResult<()> {
+        self.request_none(Requests::Panic)
+    }
+
+    /// Starts collecting draw requests made on this python thread instead of sending them, until
+    /// the frame is ended
+    pub fn begin_frame(&self) -> PyResult<()> {
+        self.check_open()?;
+        let mut batches = self.batches.lock().unwrap();
+        if batches.contains_key(&thread::current().id()) {
+            return Err(LightArrangementError::new_err(
+                "A frame is already being built for this light arrangement",
+            ));
         }
+        batches.insert(thread::current().id(), Vec::new());
+        Ok(())
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Expected None response internally but got value",
-            )),
-            Err(_) => {
-                return Err(PyValueError::new_err(
-                    "Failed to receive response from Light Arrangement thread",
-                ));
+    /// Sends every draw request collected on this python thread since `begin_frame` as one batch,
+    /// showing the lights afterwards if `show` is set
+    pub fn end_frame(&self, show: bool) -> PyResult<()> {
+        let batch = self.batches.lock().unwrap().remove(&thread::current().id());
+        let mut requests = match batch {
+            Some(requests) => requests,
+            None => {
+                return Err(LightArrangementError::new_err(
+                    "No frame is being built for this light arrangement",
+                ))
             }
+        };
+        if show {
+            requests.push(Requests::Show);
         }
+        self.send_draw(Requests::Batch(requests))
+    }
+
+    /// Colors a headless strip last showed, after brightness and color correction, or None for
+    /// other strips. Still readable once closed, to check what the shutdown policy showed
+    pub fn shown_frame(&self) -> Option<Vec<PythonReturnColor>> {
+        let shown = match &self.params.strip {
+            StripParams::Headless(shown) => shown.lock().unwrap(),
+            _ => return None,
+        };
+        let channels = self.channels();
+        Some(
+            shown
+                .iter()
+                .map(|light| PythonReturnColor(light[..channels].to_vec()))
+                .collect(),
+        )
     }
 
-    pub fn fill(&self, color: PythonColor) -> PyResult<()> {
-        let send_result = self.request_sender.send(Requests::Fill(color));
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+    /// Drops every draw request collected on this python thread since `begin_frame` without
+    /// sending them
+    pub fn discard_frame(&self) {
+        self.batches.lock().unwrap().remove(&thread::current().id());
+    }
+
+    /// Sends `request` to the light arrangement thread and waits for its response, converting error
+    /// responses into the matching python exception
+    fn request(&self, request: Requests<N>) -> PyResult<Responses> {
+        let timeout = {
+            let mut responses = self.responses.lock().unwrap();
+            self.check_usable()?;
+            read_unread_responses(&self.worker(), &mut responses, false)?;
+            responses.timeout
+        };
+
+        // The response comes back on its own channel, so other python threads can make requests
+        // while this one waits
+        let worker = self.worker();
+        let reply_receiver = worker.send(request).ok_or_else(send_error)?;
+        match receive(&reply_receiver, timeout) {
+            Err(ReceiveError::TimedOut(error)) => {
+                worker.poison();
+                Err(error)
+            }
+            Err(ReceiveError::Failed(error)) => Err(error),
+            Ok(response) => {
+                if let Responses::Panicked(reason) = &response {
+                    self.responses.lock().unwrap().last_worker_error = Some(reason.clone());
+                }
+                response_to_result(response)
+            }
         }
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
-                "Expected None response internally but got value",
-            )),
-            _ => Err(PyValueError::new_err(
-                "Got wrong response internally from Light Arrangement thread",
+    /// Sends a draw request to the light arrangement thread, or adds it to the frame this python
+    /// thread is building if there is one
+    fn submit(&self, request: Requests<N>) -> PyResult<()> {
+        if let Some(batch) = self
+            .batches
+            .lock()
+            .unwrap()
+            .get_mut(&thread::current().id())
+        {
+            batch.push(request);
+            return Ok(());
+        }
+        self.send_draw(request)
+    }
+
+    /// Sends a draw request to the light arrangement thread, only waiting for it to finish if not
+    /// in asynchronous mode
+    fn send_draw(&self, request: Requests<N>) -> PyResult<()> {
+        if !self.asynchronous.load(Ordering::SeqCst) {
+            return self.request_none(request);
+        }
+
+        let mut responses = self.responses.lock().unwrap();
+        self.check_usable()?;
+        let reply_receiver = self.worker().send(request).ok_or_else(send_error)?;
+        responses.unread.push(reply_receiver);
+        Ok(())
+    }
+
+    /// Converts `color` to have as many values as the strip has channels, adding a white value to
+    /// colors sent to RGBW strips without one
+    fn color_for_strip(&self, color: PythonColor) -> PyResult<PythonColor> {
+        match (color.len(), self.channels()) {
+            (3, 4) if self.extract_white() => Ok(extract_white(&color)),
+            (3, 4) => Ok(vec![color[0], color[1], color[2], 0]),
+            (4, 3) => Err(InvalidColorError::new_err(
+                "Color has a white value but the strip has no white channel",
             )),
+            _ => Ok(color),
         }
     }
 
-    pub fn show(&self) -> PyResult<()> {
-        let send_result = self.request_sender.send(Requests::Show);
-        if let Err(_) = send_result {
-            return Err(PyValueError::new_err("Unable to send request"));
+    /// Changes the output settings with `update` and sends them to the light arrangement thread,
+    /// only keeping the change if the thread accepted it
+    fn update_output_settings(&self, update: impl FnOnce(&mut OutputSettings)) -> PyResult<()> {
+        let mut output_settings = self.output_settings.lock().unwrap().clone();
+        update(&mut output_settings);
+        self.request_none(Requests::SetOutputSettings(output_settings.clone()))?;
+        *self.output_settings.lock().unwrap() = output_settings;
+        Ok(())
+    }
+
+    /// The currently running light arrangement thread
+    fn worker(&self) -> Arc<Worker<N>> {
+        self.worker.read().unwrap().clone()
+    }
+
+    fn check_open(&self) -> PyResult<()> {
+        if self.is_closed() {
+            return Err(ArrangementClosedError::new_err(
+                "Light arrangement was closed; create a new one to keep controlling the lights",
+            ));
         }
+        Ok(())
+    }
 
-        match self.response_receiver.recv() {
-            Ok(Responses::None) => Ok(()),
-            Ok(Responses::Error(reason)) => Err(PyValueError::new_err(reason)),
-            Ok(_) => Err(PyValueError::new_err(
+    /// Errors if the arrangement was closed or a response timed out
+    fn check_usable(&self) -> PyResult<()> {
+        self.check_open()?;
+        if self.is_poisoned() {
+            return Err(WorkerThreadError::new_err(
+                "Light arrangement can no longer be used since a response from its thread timed \
+                out; close it and create a new one",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sends `request` to the light arrangement thread, expecting no value back
+    fn request_none(&self, request: Requests<N>) -> PyResult<()> {
+        match self.request(request)? {
+            Responses::None => Ok(()),
+            _ => Err(WorkerThreadError::new_err(
                 "Expected None response internally but got value",
             )),
-            _ => Err(PyValueError::new_err(
-                "Got wrong response internally from Light Arrangement thread",
+        }
+    }
+
+    /// Waits for the newly spawned light arrangement thread to report whether it started, returning
+    /// the worker to control it if it did
+    fn wait_for_init(
+        request_sender: Sender<Envelope<N>>,
+        init_receiver: Receiver<Responses>,
+        handle: JoinHandle<()>,
+        number_lights: i32,
+    ) -> SpawnResult<N> {
+        match init_receiver.recv() {
+            Ok(Responses::InitOk) => Ok((Worker::new(request_sender, handle), number_lights)),
+            Ok(Responses::InitFailed(reason)) => Err(init_error(reason)),
+            Ok(_) => Err(WorkerThreadError::new_err(
+                "Failed to start light arrangement thread; Internally returned wrong response",
             )),
+            Err(_) => match handle.join() {
+                Err(payload) => Err(WorkerThreadError::new_err(format!(
+                    "Light arrangement thread panicked while starting: {}",
+                    panic_message(payload)
+                ))),
+                Ok(_) => Err(WorkerThreadError::new_err(
+                    "Failed to start light thread due to receive error",
+                )),
+            },
         }
     }
+}
 
-    pub fn number_lights(&self) -> i32 {
-        self.number_lights
+/// Errors unless `max_milliamps`, if set, and `volts` are positive numbers
+fn check_power_limit(max_milliamps: Option<f64>, volts: f64) -> PyResult<()> {
+    if let Some(max_milliamps) = max_milliamps {
+        if !max_milliamps.is_finite() || max_milliamps <= 0.0 {
+            return Err(LightArrangementError::new_err(format!(
+                "Max milliamps must be a positive number, got {}",
+                max_milliamps
+            )));
+        }
+    }
+    if !volts.is_finite() || volts <= 0.0 {
+        return Err(LightArrangementError::new_err(format!(
+            "Volts must be a positive number, got {}",
+            volts
+        )));
+    }
+    Ok(())
+}
+
+/// Tells the main thread that the light arrangement was created
+fn send_init_ok(init_sender: &Sender<Responses>) {
+    if init_sender.send(Responses::InitOk).is_err() {
+        error!("Failed to send back to main thread that light arrangement succesfully started");
+    }
+}
+
+/// Tells the main thread that the light arrangement could not be created, and why
+fn send_init_failed(init_sender: &Sender<Responses>, reason: String) {
+    debug!("{}", reason);
+    if init_sender.send(Responses::InitFailed(reason)).is_err() {
+        error!("Failed to send back to main thread that light arrangement thread failed to start");
     }
 }
+
+/// Reads the responses to asynchronous draw requests, keeping any errors to raise later. If `wait`
+/// is set this waits for every response, otherwise it only reads the ones that have arrived
+fn read_unread_responses<const N: usize>(
+    worker: &Worker<N>,
+    responses: &mut ResponseState,
+    wait: bool,
+) -> PyResult<()> {
+    while let Some(reply_receiver) = responses.unread.first() {
+        let response = if wait {
+            match receive(reply_receiver, responses.timeout) {
+                Ok(response) => response,
+                Err(ReceiveError::TimedOut(error)) => {
+                    worker.poison();
+                    return Err(error);
+                }
+                Err(ReceiveError::Failed(error)) => return Err(error),
+            }
+        } else {
+            match reply_receiver.try_recv() {
+                Ok(response) => response,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Err(receive_error()),
+            }
+        };
+        responses.unread.remove(0);
+        if let Responses::Panicked(reason) = &response {
+            responses.last_worker_error = Some(reason.clone());
+        }
+        if let Err(error) = response_to_result(response) {
+            responses.errors.push(error);
+        }
+    }
+    Ok(())
+}
+
+/// Why waiting for a response failed
+enum ReceiveError {
+    /// No response arrived within the timeout, so the thread is likely stuck
+    TimedOut(PyErr),
+    /// The thread stopped before it responded
+    Failed(PyErr),
+}
+
+/// Waits for the response on `reply_receiver`, giving up after `timeout` if it is set
+fn receive(
+    reply_receiver: &Receiver<Responses>,
+    timeout: Option<Duration>,
+) -> Result<Responses, ReceiveError> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => {
+            return reply_receiver
+                .recv()
+                .map_err(|_| ReceiveError::Failed(receive_error()))
+        }
+    };
+
+    match reply_receiver.recv_timeout(timeout) {
+        Ok(response) => Ok(response),
+        Err(RecvTimeoutError::Timeout) => Err(ReceiveError::TimedOut(
+            ResponseTimeoutError::new_err(format!(
+                "Light Arrangement thread did not respond within {} seconds",
+                timeout.as_secs_f64()
+            )),
+        )),
+        Err(RecvTimeoutError::Disconnected) => Err(ReceiveError::Failed(receive_error())),
+    }
+}
+
+/// Raises the first error kept from asynchronous draw requests, forgetting the rest
+fn raise_async_error(responses: &mut ResponseState) -> PyResult<()> {
+    if responses.errors.is_empty() {
+        return Ok(());
+    }
+    Err(responses.errors.drain(..).next().unwrap())
+}
+
+/// Converts error responses into the matching python exception
+fn response_to_result(response: Responses) -> PyResult<Responses> {
+    match response {
+        Responses::Error(reason) => Err(LightArrangementError::new_err(reason)),
+        Responses::IndexOutOfRange(reason) => Err(IndexOutOfRangeError::new_err(reason)),
+        Responses::Panicked(reason) => Err(WorkerThreadError::new_err(format!(
+            "Light Arrangement thread panicked while handling the request: {}",
+            reason
+        ))),
+        response => Ok(response),
+    }
+}
+
+/// Checks a lookup table has a value for each of the 256 values of a channel
+fn to_lookup_table(table: Vec<u8>) -> PyResult<[u8; 256]> {
+    let length = table.len();
+    table.try_into().map_err(|_| {
+        LightArrangementError::new_err(format!(
+            "Lookup tables must have 256 values, got {}",
+            length
+        ))
+    })
+}
+
+fn send_error() -> PyErr {
+    WorkerThreadError::new_err("Unable to send request to Light Arrangement thread")
+}
+
+fn receive_error() -> PyErr {
+    WorkerThreadError::new_err("Failed to receive response from Light Arrangement thread")
+}
+
+/// Error for when the light arrangement thread answers a request with the wrong kind of response
+fn wrong_response_error() -> PyErr {
+    WorkerThreadError::new_err("Got wrong response internally from Light Arrangement thread")
+}
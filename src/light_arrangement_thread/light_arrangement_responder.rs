@@ -1,278 +1,799 @@
 /// Handles the work done by the thread that holds the LightArrangement Object.
 /// Waits for requests from the main python thread, and sends back responses after doing the
 /// computation work on this thread
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::mpsc::{Receiver, Sender};
 
-use light_arrangements::{LightArrangement, LightStrip, Loc};
+use light_arrangements::{Color, LightArrangement, LightStrip, Loc};
+use log::{debug, warn};
 
-use crate::{types::PythonColor, util::vec_to_color};
+use crate::{
+    output_strip::{
+        OutputSettings, SharedOutputSettings, SharedStripMode, StripMode, StripModeGuard,
+    },
+    rgbw_strip::WhiteChannel,
+    types::{PythonColor, PythonFrame, PythonReturnColor},
+    util::vec_to_color,
+};
 
-use super::{requests::Requests, responses::Responses, LightArrangementThread};
+use super::{
+    blend::{Blend, BlendMode},
+    falloff::Falloff,
+    requests::{Envelope, Requests},
+    responses::Responses,
+    shutdown::ShutdownPolicy,
+    LightArrangementThread,
+};
+
+/// What the light arrangement thread keeps about the lights besides the light arrangement
+struct Lights<const N: usize> {
+    locations: Vec<Loc<N>>,
+    /// White channel of RGBW strips
+    white: Option<WhiteChannel>,
+    /// Mode of the strip the light arrangement sets colors on
+    strip_mode: SharedStripMode,
+}
 
 impl<const N: usize> LightArrangementThread<N> {
     pub fn light_arrangement_thread<T: LightStrip>(
         mut light_arrangement: LightArrangement<T, N>,
-        request_receiver: Receiver<Requests<N>>,
-        response_sender: Sender<Responses>,
+        light_locations: Vec<Loc<N>>,
+        white: Option<WhiteChannel>,
+        strip_mode: SharedStripMode,
+        output_settings: SharedOutputSettings,
+        request_receiver: Receiver<Envelope<N>>,
     ) {
-        let mut listening = true;
+        debug!("Started LightArrangement thread");
+        let lights = Lights {
+            locations: light_locations,
+            white,
+            strip_mode,
+        };
+        let mut shutdown_policy = ShutdownPolicy::Blank;
 
-        while listening {
-            let request = request_receiver.recv();
-            match request {
+        loop {
+            let envelope = match request_receiver.recv() {
+                Ok(envelope) => envelope,
                 Err(_) => {
-                    eprintln!(
-                        "Got recv error when trying to read request response in other thread"
-                    );
-                    listening = false;
+                    warn!("Got recv error when trying to read request response in other thread");
+                    break;
+                }
+            };
+
+            let response = match envelope.request {
+                Requests::Quit => break,
+                Requests::SetShutdownPolicy(policy) => {
+                    shutdown_policy = policy;
+                    Responses::None
+                }
+                Requests::SetOutputSettings(settings) => {
+                    *output_settings.borrow_mut() = settings;
+                    Responses::None
                 }
-                Ok(request) => match request {
-                    Requests::Quit => {
-                        listening = false;
-                    }
-                    Requests::GetClosest(loc, max_search_distance) => Self::thread_get_closest(
-                        &light_arrangement,
-                        &response_sender,
-                        &loc,
-                        max_search_distance,
-                        &mut listening,
-                    ),
-                    Requests::GetByIndex(index) => Self::thread_get_by_index(
-                        &mut light_arrangement,
-                        &response_sender,
-                        index,
-                        &mut listening,
-                    ),
-                    Requests::SetClosest(loc, max_search_distance, color) => {
-                        Self::thread_set_closest(
-                            &mut light_arrangement,
-                            &response_sender,
-                            &loc,
-                            max_search_distance,
-                            &color,
-                            &mut listening,
-                        )
-                    }
-                    Requests::SetDecreasingIntensity(loc, set_distance, color) => {
-                        Self::thread_set_decreasing_intensity(
-                            &mut light_arrangement,
-                            &response_sender,
-                            &loc,
-                            set_distance,
-                            &color,
-                            &mut listening,
-                        )
-                    }
-                    Requests::SetDecreasingIntensityMerge(loc, set_distance, color) => {
-                        Self::thread_set_decreasing_intensity_merge(
-                            &mut light_arrangement,
-                            &response_sender,
-                            &loc,
-                            set_distance,
-                            &color,
-                            &mut listening,
-                        )
-                    }
-                    Requests::SetBox(loc1, loc2, color) => Self::thread_set_all_in_box(
-                        &mut light_arrangement,
-                        &response_sender,
-                        &loc1,
-                        &loc2,
-                        &color,
-                        &mut listening,
-                    ),
-                    Requests::SetRadius(loc, radius, color) => Self::thread_set_all_in_radius(
-                        &mut light_arrangement,
-                        &response_sender,
-                        &loc,
-                        radius,
-                        &color,
-                        &mut listening,
-                    ),
-                    Requests::SetByIndex(index, color) => Self::thread_set_by_index(
-                        &mut light_arrangement,
-                        &response_sender,
-                        index,
-                        &color,
-                        &mut listening,
-                    ),
-                    Requests::Fill(color) => Self::thread_fill(
-                        &mut light_arrangement,
-                        &response_sender,
-                        &color,
-                        &mut listening,
-                    ),
-                    Requests::Show => {
-                        Self::thread_show(&mut light_arrangement, &response_sender, &mut listening)
-                    }
-                },
+                Requests::GetEstimatedMilliamps => Self::thread_get_estimated_milliamps(
+                    &light_arrangement,
+                    lights.white.as_ref(),
+                    &output_settings.borrow(),
+                ),
+                request => {
+                    Self::handle_request_catching_panics(&mut light_arrangement, &lights, request)
+                }
+            };
+            send_response_log_error(&envelope.reply_sender, response);
+        }
+
+        Self::apply_shutdown_policy(
+            &mut light_arrangement,
+            lights.white.as_ref(),
+            &shutdown_policy,
+        );
+        debug!("Exiting LightArrangement thread");
+    }
+
+    /// Sets the lights to what `shutdown_policy` says they should show once the thread stops
+    fn apply_shutdown_policy<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        white: Option<&WhiteChannel>,
+        shutdown_policy: &ShutdownPolicy,
+    ) {
+        match shutdown_policy {
+            ShutdownPolicy::Keep => return,
+            ShutdownPolicy::Blank => {
+                Self::thread_fill(light_arrangement, white, &vec![0, 0, 0, 0], &Blend::REPLACE);
+            }
+            ShutdownPolicy::Color(color) => {
+                Self::thread_fill(light_arrangement, white, color, &Blend::REPLACE);
             }
         }
+        light_arrangement.show();
+    }
+
+    /// Does the work for a single request like `handle_request`, but turns a panic into a
+    /// response so the thread keeps serving later requests instead of dying
+    fn handle_request_catching_panics<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        request: Requests<N>,
+    ) -> Responses {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            Self::handle_request(light_arrangement, lights, request)
+        }));
+        match result {
+            Ok(response) => response,
+            Err(payload) => Responses::Panicked(panic_message(payload)),
+        }
+    }
 
-        println!("Exiting LightArrangement thread");
+    /// Does the work for a single request, returning the response to send back
+    fn handle_request<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        request: Requests<N>,
+    ) -> Responses {
+        let white = lights.white.as_ref();
+        match request {
+            Requests::GetClosest(loc, max_search_distance) => {
+                Self::thread_get_closest(light_arrangement, lights, &loc, max_search_distance)
+            }
+            Requests::GetByIndex(index) => {
+                Self::thread_get_by_index(light_arrangement, white, index)
+            }
+            Requests::GetBox(loc1, loc2) => {
+                let indices = Self::lights_in_box(light_arrangement, lights, &loc1, &loc2);
+                Self::thread_get_selection(light_arrangement, lights, indices)
+            }
+            Requests::GetRadius(loc, radius) => {
+                let indices = Self::lights_in_radius(light_arrangement, lights, &loc, radius)
+                    .into_iter()
+                    .map(|(index, _)| index);
+                Self::thread_get_selection(light_arrangement, lights, indices)
+            }
+            Requests::SetClosest(loc, max_search_distance, color, blend) => {
+                Self::thread_set_closest(
+                    light_arrangement,
+                    lights,
+                    &loc,
+                    max_search_distance,
+                    &color,
+                    &blend,
+                )
+            }
+            Requests::SetDecreasingIntensity(loc, set_distance, color, falloff) => {
+                Self::thread_set_decreasing_intensity(
+                    light_arrangement,
+                    lights,
+                    &loc,
+                    set_distance,
+                    &color,
+                    falloff.as_ref(),
+                )
+            }
+            Requests::SetDecreasingIntensityMerge(loc, set_distance, color, falloff) => {
+                Self::thread_set_decreasing_intensity_merge(
+                    light_arrangement,
+                    lights,
+                    &loc,
+                    set_distance,
+                    &color,
+                    falloff.as_ref(),
+                )
+            }
+            Requests::SetBox(loc1, loc2, color, blend) => {
+                Self::thread_set_all_in_box(light_arrangement, lights, &loc1, &loc2, &color, &blend)
+            }
+            Requests::SetRadius(loc, radius, color, blend) => Self::thread_set_all_in_radius(
+                light_arrangement,
+                lights,
+                &loc,
+                radius,
+                &color,
+                &blend,
+            ),
+            Requests::SetByIndex(index, color, blend) => {
+                Self::thread_set_by_index(light_arrangement, white, index, &color, &blend)
+            }
+            Requests::GetAll => Self::thread_get_all(light_arrangement, white),
+            Requests::GetPositions => Self::thread_get_positions(&lights.locations),
+            Requests::GetPosition(index) => Self::thread_get_position(&lights.locations, index),
+            Requests::SetAll(colors) => Self::thread_set_all(light_arrangement, white, &colors),
+            Requests::Fill(color, blend) => {
+                Self::thread_fill(light_arrangement, white, &color, &blend)
+            }
+            Requests::Show => Self::thread_show(light_arrangement),
+            #[cfg(debug_assertions)]
+            Requests::Panic => panic!("Panic requested for testing"),
+            Requests::Batch(requests) => Self::thread_batch(light_arrangement, lights, requests),
+            Requests::Quit
+            | Requests::SetShutdownPolicy(_)
+            | Requests::SetOutputSettings(_)
+            | Requests::GetEstimatedMilliamps => control_in_batch_error(),
+        }
     }
 
     fn thread_get_closest<T: LightStrip>(
         light_arrangement: &LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        lights: &Lights<N>,
         loc: &Loc<N>,
         max_search_distance: f64,
-        listening: &mut bool,
-    ) {
-        let result = match light_arrangement.get_closest(&loc, max_search_distance) {
-            None => Responses::OptionColorResponse(None),
-            Some(color) => {
-                Responses::OptionColorResponse(Some((color.red, color.green, color.blue)))
-            }
-        };
-        send_response_print_error(response_sender, result, listening);
+    ) -> Responses {
+        // Reading the white channel through the same call finds the same light
+        let white = lights.white.as_ref().map(|_| {
+            let _white_mode = StripModeGuard::new(&lights.strip_mode, StripMode::White);
+            light_arrangement
+                .get_closest(loc, max_search_distance)
+                .map_or(0, |grey| grey.red)
+        });
+        let color = light_arrangement
+            .get_closest(loc, max_search_distance)
+            .map(|color| return_color(&color, white));
+        Responses::OptionColorResponse(color)
     }
 
     fn thread_get_by_index<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        white: Option<&WhiteChannel>,
         index: usize,
-        listening: &mut bool,
-    ) {
-        if index >= light_arrangement.number_lights() {
-            send_response_print_error(
-                response_sender,
-                Responses::Error(
-                    format!(
-                        "Index {} is out of bounds for light strip with {} lights",
-                        index,
-                        light_arrangement.number_lights()
-                    )
-                    .to_string(),
-                ),
-                listening,
-            );
-            return;
+    ) -> Responses {
+        if let Some(error) = index_error(index, light_arrangement.number_lights()) {
+            return error;
         }
 
         let color = light_arrangement.get_by_index(index);
-        send_response_print_error(
-            response_sender,
-            Responses::ColorResponse((color.red, color.green, color.blue)),
-            listening,
-        );
+        let white = white.map(|white| white.borrow()[index]);
+        Responses::ColorResponse(return_color(&color, white))
+    }
+
+    fn thread_get_all<T: LightStrip>(
+        light_arrangement: &LightArrangement<T, N>,
+        white: Option<&WhiteChannel>,
+    ) -> Responses {
+        let channels = if white.is_some() { 4 } else { 3 };
+        let mut colors = Vec::with_capacity(light_arrangement.number_lights() * channels);
+        for index in 0..light_arrangement.number_lights() {
+            let color = light_arrangement.get_by_index(index);
+            colors.extend_from_slice(&[color.red, color.green, color.blue]);
+            if let Some(white) = white {
+                colors.push(white.borrow()[index]);
+            }
+        }
+        Responses::FrameResponse(colors)
+    }
+
+    /// Estimates the current the lights draw once they are shown with `output_settings`
+    fn thread_get_estimated_milliamps<T: LightStrip>(
+        light_arrangement: &LightArrangement<T, N>,
+        white: Option<&WhiteChannel>,
+        output_settings: &OutputSettings,
+    ) -> Responses {
+        let white = white.map(|white| white.borrow());
+        let lights: Vec<[u8; 4]> = (0..light_arrangement.number_lights())
+            .map(|index| {
+                let color = light_arrangement.get_by_index(index);
+                let white = white.as_ref().map_or(0, |white| white[index]);
+                [color.red, color.green, color.blue, white]
+            })
+            .collect();
+        let output = output_settings.output(&lights);
+        Responses::MilliampsResponse(output_settings.estimate_milliamps(&output))
+    }
+
+    /// Index, position and color of every light in `indices`
+    fn thread_get_selection<T: LightStrip>(
+        light_arrangement: &LightArrangement<T, N>,
+        lights: &Lights<N>,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Responses {
+        let white = lights.white.as_ref().map(|white| white.borrow());
+        let selection = indices
+            .into_iter()
+            .map(|index| {
+                let color = return_color(
+                    &light_arrangement.get_by_index(index),
+                    white.as_ref().map(|white| white[index]),
+                );
+                (index, lights.locations[index].coords.to_vec(), color)
+            })
+            .collect();
+        Responses::SelectionResponse(selection)
+    }
+
+    fn thread_get_positions(light_locations: &[Loc<N>]) -> Responses {
+        let positions = light_locations
+            .iter()
+            .flat_map(|loc| loc.coords.iter().copied())
+            .collect();
+        Responses::PositionsResponse(positions)
+    }
+
+    fn thread_get_position(light_locations: &[Loc<N>], index: usize) -> Responses {
+        match light_locations.get(index) {
+            Some(loc) => Responses::PositionResponse(loc.coords.to_vec()),
+            None => Responses::IndexOutOfRange(format!(
+                "Index {} is out of bounds for light strip with {} lights",
+                index,
+                light_locations.len()
+            )),
+        }
     }
 
     fn thread_set_closest<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        lights: &Lights<N>,
         loc: &Loc<N>,
         max_search_distance: f64,
         color: &PythonColor,
-        listening: &mut bool,
-    ) {
-        light_arrangement.set_closest(&loc, max_search_distance, &vec_to_color(color));
-        send_response_print_error(response_sender, Responses::None, listening);
+        blend: &Blend,
+    ) -> Responses {
+        if !blend.is_replace() {
+            let index = Self::closest_light(light_arrangement, lights, loc, max_search_distance);
+            Self::blend_lights(
+                light_arrangement,
+                lights.white.as_ref(),
+                index,
+                color,
+                blend,
+            );
+            return Responses::None;
+        }
+
+        Self::set_with_white(
+            light_arrangement,
+            lights,
+            color,
+            |light_arrangement, color| {
+                light_arrangement.set_closest(loc, max_search_distance, color)
+            },
+        );
+        Responses::None
     }
 
     fn thread_set_decreasing_intensity<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        lights: &Lights<N>,
         loc: &Loc<N>,
         set_distance: f64,
         color: &PythonColor,
-        listening: &mut bool,
-    ) {
-        light_arrangement.set_decreasing_intensity(&loc, set_distance, &vec_to_color(color));
-        send_response_print_error(response_sender, Responses::None, listening);
+        falloff: Option<&Falloff>,
+    ) -> Responses {
+        if let Some(falloff) = falloff {
+            let faded = Self::lights_in_radius(light_arrangement, lights, loc, set_distance);
+            Self::fade_lights(
+                light_arrangement,
+                lights.white.as_ref(),
+                faded,
+                set_distance,
+                color,
+                falloff,
+                &Blend::REPLACE,
+            );
+            return Responses::None;
+        }
+
+        Self::set_with_white(
+            light_arrangement,
+            lights,
+            color,
+            |light_arrangement, color| {
+                light_arrangement.set_decreasing_intensity(loc, set_distance, color)
+            },
+        );
+        Responses::None
     }
 
     fn thread_set_decreasing_intensity_merge<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        lights: &Lights<N>,
         loc: &Loc<N>,
         set_distance: f64,
         color: &PythonColor,
-        listening: &mut bool,
-    ) {
-        light_arrangement.set_decreasing_intensity_merge(&loc, set_distance, &vec_to_color(color));
-        send_response_print_error(response_sender, Responses::None, listening);
+        falloff: Option<&Falloff>,
+    ) -> Responses {
+        if let Some(falloff) = falloff {
+            let faded = Self::lights_in_radius(light_arrangement, lights, loc, set_distance);
+            let merge = Blend {
+                mode: BlendMode::Max,
+                opacity: 1.0,
+            };
+            Self::fade_lights(
+                light_arrangement,
+                lights.white.as_ref(),
+                faded,
+                set_distance,
+                color,
+                falloff,
+                &merge,
+            );
+            return Responses::None;
+        }
+
+        Self::set_with_white(
+            light_arrangement,
+            lights,
+            color,
+            |light_arrangement, color| {
+                light_arrangement.set_decreasing_intensity_merge(loc, set_distance, color)
+            },
+        );
+        Responses::None
     }
 
     fn thread_set_all_in_box<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        lights: &Lights<N>,
         loc1: &Loc<N>,
         loc2: &Loc<N>,
         color: &PythonColor,
-        listening: &mut bool,
-    ) {
-        light_arrangement.set_all_in_box(loc1, loc2, &vec_to_color(color));
-        send_response_print_error(response_sender, Responses::None, listening);
+        blend: &Blend,
+    ) -> Responses {
+        if !blend.is_replace() {
+            let indices = Self::lights_in_box(light_arrangement, lights, loc1, loc2);
+            Self::blend_lights(
+                light_arrangement,
+                lights.white.as_ref(),
+                indices,
+                color,
+                blend,
+            );
+            return Responses::None;
+        }
+
+        Self::set_with_white(
+            light_arrangement,
+            lights,
+            color,
+            |light_arrangement, color| light_arrangement.set_all_in_box(loc1, loc2, color),
+        );
+        Responses::None
     }
 
     fn thread_set_all_in_radius<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        lights: &Lights<N>,
         loc: &Loc<N>,
         radius: f64,
         color: &PythonColor,
-        listening: &mut bool,
+        blend: &Blend,
+    ) -> Responses {
+        if !blend.is_replace() {
+            let indices = Self::lights_in_radius(light_arrangement, lights, loc, radius)
+                .into_iter()
+                .map(|(index, _)| index);
+            Self::blend_lights(
+                light_arrangement,
+                lights.white.as_ref(),
+                indices,
+                color,
+                blend,
+            );
+            return Responses::None;
+        }
+
+        Self::set_with_white(
+            light_arrangement,
+            lights,
+            color,
+            |light_arrangement, color| light_arrangement.set_all_in_radius(loc, radius, color),
+        );
+        Responses::None
+    }
+
+    /// Indices of the lights `set` reaches, found by running it with the strip recording the lights
+    /// set instead of changing them. Finds lights the same way as the light arrangement's own calls
+    fn probe<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        set: impl FnOnce(&mut LightArrangement<T, N>, &Color),
+    ) -> Vec<usize> {
+        let _probe_mode = StripModeGuard::new(&lights.strip_mode, StripMode::Probe(Vec::new()));
+        let black = Color {
+            red: 0,
+            green: 0,
+            blue: 0,
+        };
+        set(light_arrangement, &black);
+        let mut indices = match &mut *lights.strip_mode.borrow_mut() {
+            StripMode::Probe(indices) => std::mem::take(indices),
+            _ => Vec::new(),
+        };
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Index of the light `set_closest` sets, if one is within `max_search_distance`
+    fn closest_light<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        loc: &Loc<N>,
+        max_search_distance: f64,
+    ) -> Option<usize> {
+        Self::probe(light_arrangement, lights, |light_arrangement, color| {
+            light_arrangement.set_closest(loc, max_search_distance, color)
+        })
+        .into_iter()
+        .next()
+    }
+
+    /// Indices of every light `set_all_in_box` sets
+    fn lights_in_box<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        loc1: &Loc<N>,
+        loc2: &Loc<N>,
+    ) -> Vec<usize> {
+        Self::probe(light_arrangement, lights, |light_arrangement, color| {
+            light_arrangement.set_all_in_box(loc1, loc2, color)
+        })
+    }
+
+    /// Indices of every light `set_all_in_radius` sets, along with how far each is from `loc`
+    fn lights_in_radius<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        loc: &Loc<N>,
+        radius: f64,
+    ) -> Vec<(usize, f64)> {
+        Self::probe(light_arrangement, lights, |light_arrangement, color| {
+            light_arrangement.set_all_in_radius(loc, radius, color)
+        })
+        .into_iter()
+        .map(|index| (index, distance(loc, &lights.locations[index])))
+        .collect()
+    }
+
+    /// Runs `set` with `color`, then on RGBW strips again with the strip setting white values and
+    /// a grey color of the white value, so the white channel changes on the same lights, with the
+    /// same falloff, as the colors do
+    fn set_with_white<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        color: &PythonColor,
+        set: impl Fn(&mut LightArrangement<T, N>, &Color),
     ) {
-        light_arrangement.set_all_in_radius(loc, radius, &vec_to_color(color));
-        send_response_print_error(response_sender, Responses::None, listening);
+        set(light_arrangement, &vec_to_color(color));
+        if lights.white.is_some() {
+            let white = white_of(color);
+            let _white_mode = StripModeGuard::new(&lights.strip_mode, StripMode::White);
+            let grey = Color {
+                red: white,
+                green: white,
+                blue: white,
+            };
+            set(light_arrangement, &grey);
+        }
     }
 
     fn thread_set_by_index<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        white: Option<&WhiteChannel>,
         index: usize,
         color: &PythonColor,
-        listening: &mut bool,
-    ) {
-        if index >= light_arrangement.number_lights() {
-            send_response_print_error(
-                response_sender,
-                Responses::Error(
-                    format!(
-                        "Index {} is out of bounds for light strip with {} lights",
-                        index,
-                        light_arrangement.number_lights()
-                    )
-                    .to_string(),
-                ),
-                listening,
-            );
-            return;
+        blend: &Blend,
+    ) -> Responses {
+        if let Some(error) = index_error(index, light_arrangement.number_lights()) {
+            return error;
+        }
+
+        if !blend.is_replace() {
+            Self::blend_lights(light_arrangement, white, Some(index), color, blend);
+            return Responses::None;
         }
 
         light_arrangement.set_by_index(index, &vec_to_color(color));
-        send_response_print_error(response_sender, Responses::None, listening);
+        if let Some(white) = white {
+            white.borrow_mut()[index] = white_of(color);
+        }
+        Responses::None
+    }
+
+    fn thread_set_all<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        white: Option<&WhiteChannel>,
+        colors: &PythonFrame,
+    ) -> Responses {
+        let channels = channels(white);
+        if let Some(error) = frame_error(colors, light_arrangement.number_lights(), channels) {
+            return error;
+        }
+
+        for (index, color) in colors.chunks(channels).enumerate() {
+            light_arrangement.set_by_index(index, &vec_to_color(color));
+            if let Some(white) = white {
+                white.borrow_mut()[index] = white_of(color);
+            }
+        }
+        Responses::None
     }
 
     fn thread_fill<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
+        white: Option<&WhiteChannel>,
         color: &PythonColor,
-        listening: &mut bool,
-    ) {
+        blend: &Blend,
+    ) -> Responses {
+        if !blend.is_replace() {
+            let indices = 0..light_arrangement.number_lights();
+            Self::blend_lights(light_arrangement, white, indices, color, blend);
+            return Responses::None;
+        }
+
         light_arrangement.fill(&vec_to_color(color));
-        send_response_print_error(response_sender, Responses::None, listening);
+        if let Some(white) = white {
+            white.borrow_mut().fill(white_of(color));
+        }
+        Responses::None
     }
 
-    fn thread_show<T: LightStrip>(
+    /// Blends `color` into the color of every light in `lights`, faded by `falloff` over its
+    /// distance from where the color is set
+    fn fade_lights<T: LightStrip>(
         light_arrangement: &mut LightArrangement<T, N>,
-        response_sender: &Sender<Responses>,
-        listening: &mut bool,
+        white: Option<&WhiteChannel>,
+        lights: Vec<(usize, f64)>,
+        set_distance: f64,
+        color: &PythonColor,
+        falloff: &Falloff,
+        blend: &Blend,
     ) {
+        for (index, distance) in lights {
+            let intensity = falloff.intensity(distance, set_distance);
+            let faded: PythonColor = color
+                .iter()
+                .map(|&value| (value as f64 * intensity).round() as u8)
+                .collect();
+            Self::blend_lights(light_arrangement, white, Some(index), &faded, blend);
+        }
+    }
+
+    /// Blends `color` into the color of every light in `indices`
+    fn blend_lights<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        white: Option<&WhiteChannel>,
+        indices: impl IntoIterator<Item = usize>,
+        color: &PythonColor,
+        blend: &Blend,
+    ) {
+        for index in indices {
+            let existing = return_color(
+                &light_arrangement.get_by_index(index),
+                white.map(|white| white.borrow()[index]),
+            );
+            let blended = blend.apply(&existing.0, color);
+            light_arrangement.set_by_index(index, &vec_to_color(&blended));
+            if let Some(white) = white {
+                white.borrow_mut()[index] = white_of(&blended);
+            }
+        }
+    }
+
+    fn thread_show<T: LightStrip>(light_arrangement: &mut LightArrangement<T, N>) -> Responses {
         light_arrangement.show();
-        send_response_print_error(response_sender, Responses::None, listening);
+        Responses::None
+    }
+
+    /// Runs every request in order, after checking that none of them fail before changing the
+    /// lights, so a batch that returns an error leaves the lights as they were
+    fn thread_batch<T: LightStrip>(
+        light_arrangement: &mut LightArrangement<T, N>,
+        lights: &Lights<N>,
+        requests: Vec<Requests<N>>,
+    ) -> Responses {
+        let number_lights = light_arrangement.number_lights();
+        let channels = channels(lights.white.as_ref());
+        if let Some(error) = requests
+            .iter()
+            .find_map(|request| Self::check_batched_request(request, number_lights, channels))
+        {
+            return error;
+        }
+
+        for request in requests {
+            let response = Self::handle_request(light_arrangement, lights, request);
+            if matches!(
+                response,
+                Responses::Error(_) | Responses::IndexOutOfRange(_)
+            ) {
+                return response;
+            }
+        }
+        Responses::None
+    }
+
+    /// Error that `request` fails with before changing any lights when run in a batch, if any
+    fn check_batched_request(
+        request: &Requests<N>,
+        number_lights: usize,
+        channels: usize,
+    ) -> Option<Responses> {
+        match request {
+            Requests::SetByIndex(index, ..) => index_error(*index, number_lights),
+            Requests::SetAll(colors) => frame_error(colors, number_lights, channels),
+            Requests::Quit
+            | Requests::SetShutdownPolicy(_)
+            | Requests::SetOutputSettings(_)
+            | Requests::GetEstimatedMilliamps => Some(control_in_batch_error()),
+            _ => None,
+        }
     }
 }
 
-/// Sends `response` through `sender`, printing the error and stopping the thread if it fails
-fn send_response_print_error(
-    sender: &Sender<Responses>,
-    response: Responses,
-    listening: &mut bool,
-) {
+/// Error for an index past the end of a strip with `number_lights` lights, if it is
+fn index_error(index: usize, number_lights: usize) -> Option<Responses> {
+    if index < number_lights {
+        return None;
+    }
+    Some(Responses::IndexOutOfRange(format!(
+        "Index {} is out of bounds for light strip with {} lights",
+        index, number_lights
+    )))
+}
+
+/// Error for a frame without `channels` values for each of `number_lights` lights, if it is
+fn frame_error(colors: &[u8], number_lights: usize, channels: usize) -> Option<Responses> {
+    if colors.len() == number_lights * channels {
+        return None;
+    }
+    Some(Responses::Error(format!(
+        "Expected {} color values for {} lights but got {}",
+        number_lights * channels,
+        number_lights,
+        colors.len()
+    )))
+}
+
+/// Error for a thread control request sent in a batch, which it can't be
+fn control_in_batch_error() -> Responses {
+    Responses::Error(
+        "Thread control requests can only be sent on their own, not in a batch".to_string(),
+    )
+}
+
+/// Number of values each color has, which is 4 on RGBW strips and 3 otherwise
+fn channels(white: Option<&WhiteChannel>) -> usize {
+    if white.is_some() {
+        4
+    } else {
+        3
+    }
+}
+
+/// Converts a color from the light arrangement, and its white value on RGBW strips, to send back
+fn return_color(color: &Color, white: Option<u8>) -> PythonReturnColor {
+    let mut values = vec![color.red, color.green, color.blue];
+    values.extend(white);
+    PythonReturnColor(values)
+}
+
+/// Straight line distance between two locations
+fn distance<const N: usize>(loc1: &Loc<N>, loc2: &Loc<N>) -> f64 {
+    loc1.coords
+        .iter()
+        .zip(loc2.coords.iter())
+        .map(|(coord1, coord2)| (coord1 - coord2).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// White value of a color, which is 0 for colors without one
+fn white_of(color: &[u8]) -> u8 {
+    color.get(3).copied().unwrap_or(0)
+}
+
+/// Sends `response` through `sender`, logging a warning if the caller stopped waiting for it
+fn send_response_log_error(sender: &Sender<Responses>, response: Responses) {
     if sender.send(response).is_err() {
-        eprintln!("Error sending result!");
-        *listening = false;
+        warn!("Error sending result; the caller stopped waiting for it");
+    }
+}
+
+/// Gets the message a thread panicked with, if it was given one
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(_) => "Unknown panic".to_string(),
     }
 }
@@ -1,27 +1,116 @@
+mod blend;
+mod falloff;
 mod light_arrangement_requester;
 mod light_arrangement_responder;
 mod requests;
 mod responses;
+mod shutdown;
 
-use std::sync::mpsc::{Receiver, Sender};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI32};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::ThreadId;
+use std::time::Duration;
 
+use light_arrangements::{LightStripConfig, TestStripDisplayConfig};
+use log::{debug, warn};
+use pyo3::{PyErr, PyResult, Python};
+
+use crate::headless_strip::ShownFrame;
+use crate::output_strip::OutputSettings;
+use crate::rgbw_strip::RgbwLayout;
+
+use self::shutdown::{Worker, STOP_TIMEOUT};
 use self::{requests::Requests, responses::Responses};
 
+pub use self::blend::{Blend, BlendMode};
+pub use self::falloff::{Falloff, FalloffCurve};
+pub use self::shutdown::{stop_all_workers, ShutdownPolicy};
+
 /// Object to interface with LightArrangements that are not Send. This constructs it on a seperate thread
 /// and acesses it using thread channels. This is done to itnerface with the python API as it can
 /// not send the state object across Python threads
 pub struct LightArrangementThread<const N: usize> {
-    request_sender: Sender<Requests<N>>,
-    response_receiver: Receiver<Responses>,
-    number_lights: i32,
+    /// The running thread, replaced when the arrangement is restarted
+    worker: RwLock<Arc<Worker<N>>>,
+    /// What the thread was built from, so it can be built again on restart
+    params: ConstructionParams<N>,
+    /// Last shutdown policy that was set, so a restarted thread keeps following it
+    shutdown_policy: Mutex<ShutdownPolicy>,
+    /// Last output settings that were set, so a restarted thread keeps applying them
+    output_settings: Mutex<OutputSettings>,
+    responses: Mutex<ResponseState>,
+    number_lights: AtomicI32,
+    /// Draw requests collected by each python thread building a frame, sent together when it ends.
+    /// Kept per thread so draws from other threads aren't caught up in the frame
+    batches: Mutex<HashMap<ThreadId, Vec<Requests<N>>>>,
+    /// Whether draw requests are sent without waiting for their response
+    asynchronous: AtomicBool,
+    /// Whether colors without a white value are converted to RGBW by moving the white they share
+    /// into the white channel. Only used on RGBW strips
+    extract_white: AtomicBool,
+}
+
+/// Worker for a newly started light arrangement thread along with its number of lights
+type SpawnResult<const N: usize> = PyResult<(Arc<Worker<N>>, i32)>;
+
+/// Everything a light arrangement thread is built from
+struct ConstructionParams<const N: usize> {
+    strip: StripParams<N>,
+    input_file: String,
+    number_children_for_division: usize,
+    /// Whether the strip has a white channel on top of red, green and blue
+    rgbw: bool,
+}
+
+/// The kind of strip a light arrangement thread drives and its configuration
+enum StripParams<const N: usize> {
+    /// A strip driving real hardware, with its layout if it is an RGBW strip, along with the
+    /// function that spawns a thread for its type
+    Real(
+        LightStripConfig,
+        Option<RgbwLayout>,
+        fn(LightStripConfig, Option<RgbwLayout>, String, usize) -> SpawnResult<N>,
+    ),
+    Test(TestStripDisplayConfig),
+    /// A strip that only stores its colors, along with where it copies each frame it shows
+    Headless(ShownFrame),
+}
+
+/// Bookkeeping for responses from the light arrangement thread, including those to draw requests
+/// that were sent without waiting for their response
+struct ResponseState {
+    /// Reply channels of asynchronous draw requests whose response has not been read yet, in the
+    /// order they were sent
+    unread: Vec<Receiver<Responses>>,
+    /// Errors from asynchronous draw requests, raised on the next `show` or `flush`
+    errors: Vec<PyErr>,
+    /// How long to wait for each response, or forever if not set
+    timeout: Option<Duration>,
+    /// Message of the last panic on the light arrangement thread, if a request ever panicked
+    last_worker_error: Option<String>,
 }
 
 impl<const N: usize> Drop for LightArrangementThread<N> {
     fn drop(&mut self) {
-        let response = self.request_sender.send(Requests::Quit);
-        match response {
-            Ok(_) => println!("Succesfully dropped the light arrangement thread"),
-            Err(_) => println!("Failed to send the quit message to the light arrangement thread"),
+        let worker = self.worker.read().unwrap().clone();
+        if worker.is_closed() {
+            return;
+        }
+        // Waits for the thread to exit so it never logs while the interpreter is shutting down. The
+        // GIL is released since the thread needs it to log. A thread that takes too long is left to
+        // exit on its own rather than hanging whatever dropped the arrangement
+        let deadline = self.timeout().unwrap_or(STOP_TIMEOUT);
+        let stopped =
+            Python::with_gil(|py| py.allow_threads(|| worker.stop_unless_poisoned(Some(deadline))));
+        match stopped {
+            Some(true) => debug!("Succesfully dropped the light arrangement thread"),
+            Some(false) => warn!("Light arrangement thread panicked before it was dropped"),
+            None => warn!(
+                "Light arrangement thread was stuck or did not stop within {:?} of being dropped, leaving it to exit on its own",
+                deadline
+            ),
         };
     }
 }
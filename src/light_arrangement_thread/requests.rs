@@ -1,18 +1,46 @@
+use std::sync::mpsc::Sender;
+
 use light_arrangements::Loc;
 
-use crate::types::PythonColor;
+use crate::output_strip::OutputSettings;
+use crate::types::{PythonColor, PythonFrame};
+
+use super::blend::Blend;
+use super::falloff::Falloff;
+use super::responses::Responses;
+use super::shutdown::ShutdownPolicy;
 
 /// Data sent between the threads to request information
 pub enum Requests<const N: usize> {
     GetClosest(Loc<N>, f64),
-    SetClosest(Loc<N>, f64, PythonColor),
-    SetDecreasingIntensity(Loc<N>, f64, PythonColor),
-    SetDecreasingIntensityMerge(Loc<N>, f64, PythonColor),
-    SetBox(Loc<N>, Loc<N>, PythonColor),
-    SetRadius(Loc<N>, f64, PythonColor),
+    SetClosest(Loc<N>, f64, PythonColor, Blend),
+    SetDecreasingIntensity(Loc<N>, f64, PythonColor, Option<Falloff>),
+    SetDecreasingIntensityMerge(Loc<N>, f64, PythonColor, Option<Falloff>),
+    SetBox(Loc<N>, Loc<N>, PythonColor, Blend),
+    SetRadius(Loc<N>, f64, PythonColor, Blend),
     GetByIndex(usize),
-    SetByIndex(usize, PythonColor),
-    Fill(Vec<u8>),
+    GetBox(Loc<N>, Loc<N>),
+    GetRadius(Loc<N>, f64),
+    SetByIndex(usize, PythonColor, Blend),
+    GetAll,
+    GetPositions,
+    GetPosition(usize),
+    SetAll(PythonFrame),
+    Fill(Vec<u8>, Blend),
     Show,
+    Batch(Vec<Requests<N>>),
+    SetShutdownPolicy(ShutdownPolicy),
+    SetOutputSettings(OutputSettings),
+    GetEstimatedMilliamps,
     Quit,
+    /// Makes the thread panic, so tests can check panics are caught
+    #[cfg(debug_assertions)]
+    Panic,
+}
+
+/// A request along with the channel its response is sent back on, so each caller only ever reads
+/// the response to its own request
+pub struct Envelope<const N: usize> {
+    pub request: Requests<N>,
+    pub reply_sender: Sender<Responses>,
 }
@@ -1,11 +1,19 @@
-use crate::types::PythonReturnColor;
+use crate::types::{PythonFrame, PythonReturnColor, PythonSelection};
 
 /// Data sent between the threads to receive information
 pub enum Responses {
     InitOk,
-    InitFailed,
+    InitFailed(String),
     None,
     OptionColorResponse(Option<PythonReturnColor>),
     ColorResponse(PythonReturnColor),
+    FrameResponse(PythonFrame),
+    PositionsResponse(Vec<f64>),
+    PositionResponse(Vec<f64>),
+    SelectionResponse(PythonSelection),
+    MilliampsResponse(f64),
     Error(String),
+    IndexOutOfRange(String),
+    /// The request panicked on the light arrangement thread, with the panic message
+    Panicked(String),
 }
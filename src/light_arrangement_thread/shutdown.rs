@@ -0,0 +1,176 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::types::PythonColor;
+
+use super::requests::{Envelope, Requests};
+use super::responses::Responses;
+
+/// What the lights show once their light arrangement thread stops
+#[derive(Clone)]
+pub enum ShutdownPolicy {
+    /// Turn every light off
+    Blank,
+    /// Leave the last frame that was shown
+    Keep,
+    /// Show one color on every light
+    Color(PythonColor),
+}
+
+/// Every light arrangement thread that has not been stopped, so they can be stopped before the
+/// interpreter exits
+static RUNNING_WORKERS: Mutex<Vec<Weak<dyn Stop>>> = Mutex::new(Vec::new());
+
+/// How often `Worker::stop_within` checks whether the thread has exited
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How long a thread is waited for when its light arrangement is dropped without a timeout set, or
+/// the interpreter exits
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+trait Stop: Send + Sync {
+    fn stop_unless_poisoned(&self, deadline: Option<Duration>) -> Option<bool>;
+}
+
+/// Sending end of the requests to a light arrangement thread, and the handle to stop it
+pub struct Worker<const N: usize> {
+    request_sender: Sender<Envelope<N>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    closed: AtomicBool,
+    /// Set once a response from the thread times out, since it is likely stuck
+    poisoned: AtomicBool,
+}
+
+impl<const N: usize> Worker<N> {
+    /// Wraps a running light arrangement thread, registering it to be stopped on interpreter exit
+    pub fn new(request_sender: Sender<Envelope<N>>, handle: JoinHandle<()>) -> Arc<Self> {
+        let worker = Arc::new(Self {
+            request_sender,
+            handle: Mutex::new(Some(handle)),
+            closed: AtomicBool::new(false),
+            poisoned: AtomicBool::new(false),
+        });
+
+        let mut running_workers = RUNNING_WORKERS.lock().unwrap();
+        running_workers.retain(|running_worker| running_worker.strong_count() > 0);
+        let weak_worker: Weak<dyn Stop> = Arc::downgrade(&worker) as Weak<Worker<N>>;
+        running_workers.push(weak_worker);
+
+        worker
+    }
+
+    /// Sends `request` to the thread, returning the channel its response will arrive on, or None
+    /// if the thread has stopped
+    pub fn send(&self, request: Requests<N>) -> Option<Receiver<Responses>> {
+        let (reply_sender, reply_receiver) = channel();
+        let envelope = Envelope {
+            request,
+            reply_sender,
+        };
+        match self.request_sender.send(envelope) {
+            Ok(_) => Some(reply_receiver),
+            Err(_) => None,
+        }
+    }
+
+    /// Tells the thread to stop without waiting for it to exit
+    pub fn quit(&self) -> bool {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return true;
+        }
+        self.send(Requests::Quit).is_some()
+    }
+
+    /// Tells the thread to stop and waits for it to exit, returning false if it panicked
+    pub fn stop(&self) -> bool {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return true;
+        }
+
+        // The thread may have already stopped, in which case there is nothing to tell it
+        self.send(Requests::Quit);
+        let handle = self.handle.lock().unwrap().take();
+        match handle {
+            Some(handle) => handle.join().is_ok(),
+            None => true,
+        }
+    }
+
+    /// Tells the thread to stop and waits up to `deadline` for it to exit, returning whether it
+    /// exited without panicking, or None if it was still running and was left to finish on its own
+    pub fn stop_within(&self, deadline: Duration) -> Option<bool> {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Some(true);
+        }
+
+        self.send(Requests::Quit);
+        let start = Instant::now();
+        while self.is_running() {
+            if start.elapsed() >= deadline {
+                return None;
+            }
+            thread::sleep(STOP_POLL_INTERVAL);
+        }
+        let handle = self.handle.lock().unwrap().take();
+        match handle {
+            Some(handle) => Some(handle.join().is_ok()),
+            None => Some(true),
+        }
+    }
+
+    /// Tells the thread to stop, waiting up to `deadline` for it to exit, or for as long as it takes
+    /// if not set. Returns whether it exited without panicking, or None if it was left to exit on
+    /// its own. A poisoned thread is never waited for: it is likely stuck, so waiting for it to exit
+    /// could hang forever
+    pub fn stop_unless_poisoned(&self, deadline: Option<Duration>) -> Option<bool> {
+        if self.is_poisoned() {
+            self.quit();
+            return None;
+        }
+        match deadline {
+            Some(deadline) => self.stop_within(deadline),
+            None => Some(self.stop()),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Marks the thread as stuck, after one of its responses timed out
+    pub fn poison(&self) {
+        self.poisoned.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::SeqCst)
+    }
+
+    /// Whether the thread has not exited, such as from a panic
+    pub fn is_running(&self) -> bool {
+        match self.handle.lock().unwrap().as_ref() {
+            Some(handle) => !handle.is_finished(),
+            None => false,
+        }
+    }
+}
+
+impl<const N: usize> Stop for Worker<N> {
+    fn stop_unless_poisoned(&self, deadline: Option<Duration>) -> Option<bool> {
+        Worker::stop_unless_poisoned(self, deadline)
+    }
+}
+
+/// Stops every light arrangement thread that is still running, so each applies its shutdown policy.
+/// Threads that are stuck are left to exit on their own, so the interpreter can still exit
+pub fn stop_all_workers() {
+    let running_workers: Vec<Weak<dyn Stop>> = RUNNING_WORKERS.lock().unwrap().drain(..).collect();
+    for running_worker in running_workers {
+        if let Some(worker) = running_worker.upgrade() {
+            worker.stop_unless_poisoned(Some(STOP_TIMEOUT));
+        }
+    }
+}
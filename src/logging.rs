@@ -0,0 +1,22 @@
+use std::sync::Mutex;
+
+use pyo3::prelude::*;
+use pyo3_log::ResetHandle;
+
+/// Handle to clear the logger levels pyo3-log caches, set once the module is imported
+static RESET_HANDLE: Mutex<Option<ResetHandle>> = Mutex::new(None);
+
+/// Sends log messages to python's `logging`, under the `light_arrangements_python` logger
+pub fn init() {
+    *RESET_HANDLE.lock().unwrap() = Some(pyo3_log::init());
+}
+
+/// Makes the library pick up changes to the level of its python loggers. The level of each
+/// logger is cached the first time something is logged to it, so changes after that are missed
+/// until this is called
+#[pyfunction]
+pub fn reset_logging() {
+    if let Some(reset_handle) = RESET_HANDLE.lock().unwrap().as_ref() {
+        reset_handle.reset();
+    }
+}
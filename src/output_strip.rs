@@ -0,0 +1,279 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use light_arrangements::{Color, LightStrip};
+
+use crate::rgbw_strip::WhiteChannel;
+
+/// Current each channel of a light draws at full brightness, in milliamps
+const MILLIAMPS_PER_CHANNEL: f64 = 20.0;
+/// Current each light draws while it is off, in milliamps
+const IDLE_MILLIAMPS: f64 = 1.0;
+
+/// Value shown for every value of each of the red, green, blue and white channels
+pub type LookupTables = [[u8; 256]; 4];
+
+/// How the colors of a light arrangement are changed on their way to the lights. Never changes the
+/// colors stored in the arrangement, so they read back as they were set
+#[derive(Clone)]
+pub struct OutputSettings {
+    /// Scales every value, from 0 for off to 1 for full brightness
+    pub brightness: f64,
+    /// Replaces every value with the one shown, such as to gamma correct them. Values are shown
+    /// as they are if not set
+    pub lookup_tables: Option<Box<LookupTables>>,
+    /// Gamma the lookup tables were made from, or None if they were given directly
+    pub gamma: Option<f64>,
+    /// Scales the red, green and blue values, to make white look white on strips with a tint
+    pub white_balance: [f64; 3],
+    /// Most current the lights may draw, in milliamps. The lights are dimmed when shown to stay
+    /// within it
+    pub max_milliamps: Option<f64>,
+    /// Voltage the lights are powered at, used to estimate the power they draw
+    pub volts: f64,
+    /// Brightness the strip itself dims every value by, from 0 to 255, on strips like ws281x
+    /// strips that have one. Only used to estimate the current the lights draw
+    pub strip_brightness: u8,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            lookup_tables: None,
+            gamma: Some(1.0),
+            white_balance: [1.0; 3],
+            max_milliamps: None,
+            volts: 5.0,
+            strip_brightness: u8::MAX,
+        }
+    }
+}
+
+impl OutputSettings {
+    /// Converts the red, green, blue and white values of a light into the values to show. Values
+    /// are looked up first, then scaled
+    pub fn apply(&self, values: [u8; 4]) -> [u8; 4] {
+        let mut output = [0; 4];
+        for (channel, value) in values.iter().enumerate() {
+            let value = match &self.lookup_tables {
+                Some(lookup_tables) => lookup_tables[channel][*value as usize],
+                None => *value,
+            };
+            let scale = self.brightness * self.white_balance.get(channel).unwrap_or(&1.0);
+            output[channel] = (value as f64 * scale).round() as u8;
+        }
+        output
+    }
+
+    /// Converts the red, green, blue and white values of every light into the values to show,
+    /// dimming them all if they would draw more current than allowed
+    pub fn output(&self, lights: &[[u8; 4]]) -> Vec<[u8; 4]> {
+        let mut output: Vec<[u8; 4]> = lights.iter().map(|values| self.apply(*values)).collect();
+        if let Some(max_milliamps) = self.max_milliamps {
+            let milliamps = self.estimate_milliamps(&output);
+            if milliamps > max_milliamps {
+                // Lights that are off still draw current, so only the rest can be scaled down
+                let idle_milliamps = IDLE_MILLIAMPS * output.len() as f64;
+                let scale =
+                    ((max_milliamps - idle_milliamps) / (milliamps - idle_milliamps)).max(0.0);
+                for values in output.iter_mut() {
+                    // Rounding down so the scaled values never go over the limit
+                    *values = values.map(|value| (value as f64 * scale) as u8);
+                }
+            }
+        }
+        output
+    }
+
+    /// Estimates the current drawn by lights showing `output`, in milliamps, once the strip has
+    /// dimmed them by its own brightness
+    pub fn estimate_milliamps(&self, output: &[[u8; 4]]) -> f64 {
+        let strip_scale = self.strip_brightness as f64 / 255.0;
+        output
+            .iter()
+            .map(|values| {
+                let on: f64 = values.iter().map(|&value| value as f64 / 255.0).sum();
+                IDLE_MILLIAMPS + on * strip_scale * MILLIAMPS_PER_CHANNEL
+            })
+            .sum()
+    }
+}
+
+/// Lookup tables that raise every value, as a fraction of full brightness, to the power of
+/// `gamma`
+pub fn gamma_lookup_tables(gamma: f64) -> Box<LookupTables> {
+    let mut table = [0; 256];
+    for (value, output) in table.iter_mut().enumerate() {
+        *output = ((value as f64 / 255.0).powf(gamma) * 255.0).round() as u8;
+    }
+    Box::new([table; 4])
+}
+
+/// Lookup table that shows every value as it is
+pub fn identity_lookup_table() -> [u8; 256] {
+    let mut table = [0; 256];
+    for (value, output) in table.iter_mut().enumerate() {
+        *output = value as u8;
+    }
+    table
+}
+
+/// Output settings of a light arrangement, shared between the light arrangement thread and the
+/// strip that applies them
+pub type SharedOutputSettings = Rc<RefCell<OutputSettings>>;
+
+/// What setting colors on an `OutputStrip` changes
+#[derive(Clone, PartialEq)]
+pub enum StripMode {
+    /// The colors of the lights
+    Colors,
+    /// The white values of RGBW strips, taken from the red value of each color set. Colors read
+    /// back are grey, with every value the white value, so the light arrangement's own calls can
+    /// set the white channel with the same lights and falloff as the colors
+    White,
+    /// Nothing changes. The index of every light set is recorded instead, so the light
+    /// arrangement's own calls can find which lights a location reaches
+    Probe(Vec<usize>),
+}
+
+/// Mode of an `OutputStrip`, shared with the light arrangement thread that switches it
+pub type SharedStripMode = Rc<RefCell<StripMode>>;
+
+/// Puts a strip in a mode until dropped, even by a panic, then puts it back to setting colors
+pub struct StripModeGuard<'a> {
+    mode: &'a SharedStripMode,
+}
+
+impl<'a> StripModeGuard<'a> {
+    pub fn new(mode: &'a SharedStripMode, new_mode: StripMode) -> Self {
+        *mode.borrow_mut() = new_mode;
+        Self { mode }
+    }
+}
+
+impl Drop for StripModeGuard<'_> {
+    fn drop(&mut self) {
+        *self.mode.borrow_mut() = StripMode::Colors;
+    }
+}
+
+/// Wraps a strip to keep the colors set on it, and only apply the output settings to them when
+/// they are shown
+pub struct OutputStrip<T: LightStrip> {
+    inner: T,
+    colors: Vec<Color>,
+    settings: SharedOutputSettings,
+    /// White values set on RGBW strips
+    white: Option<WhiteChannel>,
+    /// White values the inner strip shows, for RGBW strips that can show them
+    output_white: Option<WhiteChannel>,
+    mode: SharedStripMode,
+}
+
+impl<T: LightStrip> OutputStrip<T> {
+    pub fn new(
+        inner: T,
+        number_lights: usize,
+        settings: SharedOutputSettings,
+        white: Option<WhiteChannel>,
+        output_white: Option<WhiteChannel>,
+        mode: SharedStripMode,
+    ) -> Self {
+        Self {
+            inner,
+            colors: vec![
+                Color {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                };
+                number_lights
+            ],
+            settings,
+            white,
+            output_white,
+            mode,
+        }
+    }
+
+    /// Red, green, blue and white values of every light, as they were set
+    fn lights(&self) -> Vec<[u8; 4]> {
+        let white = self.white.as_ref().map(|white| white.borrow());
+        self.colors
+            .iter()
+            .enumerate()
+            .map(|(index, color)| {
+                let white = white.as_ref().map_or(0, |white| white[index]);
+                [color.red, color.green, color.blue, white]
+            })
+            .collect()
+    }
+}
+
+impl<T: LightStrip> LightStrip for OutputStrip<T> {
+    fn get(&self, index: usize) -> Color {
+        match &*self.mode.borrow() {
+            StripMode::Colors | StripMode::Probe(_) => self.colors[index].clone(),
+            StripMode::White => {
+                let white = self.white.as_ref().map_or(0, |white| white.borrow()[index]);
+                Color {
+                    red: white,
+                    green: white,
+                    blue: white,
+                }
+            }
+        }
+    }
+
+    fn set(&mut self, index: usize, color: &Color) {
+        match &mut *self.mode.borrow_mut() {
+            StripMode::Colors => self.colors[index] = color.clone(),
+            StripMode::White => {
+                if let Some(white) = &self.white {
+                    white.borrow_mut()[index] = color.red;
+                }
+            }
+            StripMode::Probe(indices) => indices.push(index),
+        }
+    }
+
+    fn fill(&mut self, color: &Color) {
+        match &mut *self.mode.borrow_mut() {
+            StripMode::Colors => {
+                for light in self.colors.iter_mut() {
+                    *light = color.clone();
+                }
+            }
+            StripMode::White => {
+                if let Some(white) = &self.white {
+                    white.borrow_mut().fill(color.red);
+                }
+            }
+            StripMode::Probe(indices) => indices.extend(0..self.colors.len()),
+        }
+    }
+
+    fn show(&mut self) {
+        let lights = self.lights();
+        let output = self.settings.borrow().output(&lights);
+
+        for (index, [red, green, blue, _]) in output.iter().enumerate() {
+            self.inner.set(
+                index,
+                &Color {
+                    red: *red,
+                    green: *green,
+                    blue: *blue,
+                },
+            );
+        }
+        if let Some(output_white) = &self.output_white {
+            let mut output_white = output_white.borrow_mut();
+            for (index, [_, _, _, white]) in output.iter().enumerate() {
+                output_white[index] = *white;
+            }
+        }
+        self.inner.show();
+    }
+}
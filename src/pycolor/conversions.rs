@@ -0,0 +1,102 @@
+//! Conversions between RGB and the color spaces `Color` can be made from or blended in. RGB values
+//! are floats from 0 to 1 unless they are u8
+
+/// Converts a hue in degrees, and saturation and value from 0 to 1, into RGB
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> [f64; 3] {
+    let chroma = value * saturation;
+    hue_to_rgb(hue, chroma, value - chroma)
+}
+
+/// Converts a hue in degrees, and saturation and lightness from 0 to 1, into RGB
+pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [f64; 3] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    hue_to_rgb(hue, chroma, lightness - chroma / 2.0)
+}
+
+/// Shared part of the HSV and HSL conversions, given the hue, chroma and the amount added to every
+/// channel
+fn hue_to_rgb(hue: f64, chroma: f64, offset: f64) -> [f64; 3] {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let [red, green, blue] = match sector as u32 {
+        0 => [chroma, second, 0.0],
+        1 => [second, chroma, 0.0],
+        2 => [0.0, chroma, second],
+        3 => [0.0, second, chroma],
+        4 => [second, 0.0, chroma],
+        _ => [chroma, 0.0, second],
+    };
+    [red + offset, green + offset, blue + offset]
+}
+
+/// Approximates the color of light glowing at `kelvin` degrees, valid from 1000K to 40000K
+pub fn kelvin_to_rgb(kelvin: f64) -> [f64; 3] {
+    let temperature = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (temperature - 60.0).powf(-0.1332047592)
+    };
+    let green = if temperature <= 66.0 {
+        99.4708025861 * temperature.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (temperature - 60.0).powf(-0.0755148492)
+    };
+    let blue = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (temperature - 10.0).ln() - 305.0447927307
+    };
+    [red / 255.0, green / 255.0, blue / 255.0]
+}
+
+/// Converts an RGB value from 0 to 1 into a u8, clamping values outside that range
+pub fn to_u8(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Converts sRGB into the OKLab perceptual color space, where straight lines between colors look
+/// like even blends
+pub fn rgb_to_oklab(rgb: [u8; 3]) -> [f64; 3] {
+    let [red, green, blue] = rgb.map(|value| srgb_to_linear(value as f64 / 255.0));
+    let long = (0.4122214708 * red + 0.5363325363 * green + 0.0514459929 * blue).cbrt();
+    let medium = (0.2119034982 * red + 0.6806995451 * green + 0.1073969566 * blue).cbrt();
+    let short = (0.0883024619 * red + 0.2817188376 * green + 0.6299787005 * blue).cbrt();
+    [
+        0.2104542553 * long + 0.7936177850 * medium - 0.0040720468 * short,
+        1.9779984951 * long - 2.4285922050 * medium + 0.4505937099 * short,
+        0.0259040371 * long + 0.7827717662 * medium - 0.8086757660 * short,
+    ]
+}
+
+/// Converts a color in the OKLab color space back into sRGB
+pub fn oklab_to_rgb(lab: [f64; 3]) -> [u8; 3] {
+    let [lightness, a, b] = lab;
+    let long = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let medium = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let short = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * long - 3.3077115913 * medium + 0.2309699292 * short,
+        -1.2684380046 * long + 2.6097574011 * medium - 0.3413193965 * short,
+        -0.0041960863 * long - 0.7034186147 * medium + 1.7076147010 * short,
+    ]
+    .map(|value| to_u8(linear_to_srgb(value)))
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.max(0.0).powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use pyo3::basic::CompareOp;
+use pyo3::prelude::*;
+use pyo3::types::PyType;
+
+use crate::types::{PythonColor, PythonReturnColor};
+use crate::util::hex_to_color;
+
+use self::conversions::{hsl_to_rgb, hsv_to_rgb, kelvin_to_rgb, oklab_to_rgb, rgb_to_oklab, to_u8};
+
+mod conversions;
+
+/// A color that can be passed to any light arrangement method that takes one. Has red, green and
+/// blue values from 0 to 255, and a white value for RGBW strips
+#[pyclass(name = "Color", freelist = 16)]
+#[derive(Clone, PartialEq)]
+pub struct PyColor {
+    #[pyo3(get)]
+    pub r: u8,
+    #[pyo3(get)]
+    pub g: u8,
+    #[pyo3(get)]
+    pub b: u8,
+    #[pyo3(get)]
+    pub w: Option<u8>,
+}
+
+impl PyColor {
+    fn from_rgb([r, g, b]: [u8; 3]) -> Self {
+        Self { r, g, b, w: None }
+    }
+
+    /// Color values to send to the light arrangement thread
+    pub fn to_python_color(&self) -> PythonColor {
+        let mut values = vec![self.r, self.g, self.b];
+        values.extend(self.w);
+        values
+    }
+}
+
+#[pymethods]
+impl PyColor {
+    #[new]
+    #[pyo3(signature = (r, g, b, w = None))]
+    pub fn new(r: u8, g: u8, b: u8, w: Option<u8>) -> Self {
+        Self { r, g, b, w }
+    }
+
+    #[classmethod]
+    #[pyo3(signature = (r, g, b, w = None))]
+    pub fn rgb(_cls: &PyType, r: u8, g: u8, b: u8, w: Option<u8>) -> Self {
+        Self { r, g, b, w }
+    }
+
+    /// Color from a hue in degrees, and saturation and value from 0 to 1
+    #[classmethod]
+    pub fn hsv(_cls: &PyType, h: f64, s: f64, v: f64) -> Self {
+        Self::from_rgb(hsv_to_rgb(h, s, v).map(to_u8))
+    }
+
+    /// Color from a hue in degrees, and saturation and lightness from 0 to 1
+    #[classmethod]
+    pub fn hsl(_cls: &PyType, h: f64, s: f64, l: f64) -> Self {
+        Self::from_rgb(hsl_to_rgb(h, s, l).map(to_u8))
+    }
+
+    /// Color from a hex string like "#ff8800", or "#ff880040" to include a white value
+    #[classmethod]
+    pub fn hex(_cls: &PyType, hex: &str) -> PyResult<Self> {
+        let values = hex_to_color(hex)?;
+        Ok(Self {
+            r: values[0],
+            g: values[1],
+            b: values[2],
+            w: values.get(3).copied(),
+        })
+    }
+
+    /// Color of light glowing at a temperature in kelvin, such as 2700 for warm white
+    #[classmethod]
+    pub fn kelvin(_cls: &PyType, kelvin: f64) -> Self {
+        Self::from_rgb(kelvin_to_rgb(kelvin).map(to_u8))
+    }
+
+    /// Blends from this color to `other`, where `t` is 0 for this color and 1 for `other`. Blends
+    /// in the OKLab color space, so the colors in between look evenly spaced
+    pub fn lerp(&self, other: &PyColor, t: f64) -> Self {
+        let start = rgb_to_oklab([self.r, self.g, self.b]);
+        let end = rgb_to_oklab([other.r, other.g, other.b]);
+        let mut lab = [0.0; 3];
+        for channel in 0..3 {
+            lab[channel] = start[channel] + (end[channel] - start[channel]) * t;
+        }
+
+        let w = match (self.w, other.w) {
+            (None, None) => None,
+            (w1, w2) => {
+                let (w1, w2) = (w1.unwrap_or(0) as f64, w2.unwrap_or(0) as f64);
+                Some((w1 + (w2 - w1) * t).clamp(0.0, 255.0).round() as u8)
+            }
+        };
+        Self {
+            w,
+            ..Self::from_rgb(oklab_to_rgb(lab))
+        }
+    }
+
+    /// Mixes `amount` of `other` into this color, blending in the OKLab color space like `lerp`
+    #[pyo3(signature = (other, amount = 0.5))]
+    pub fn mix(&self, other: &PyColor, amount: f64) -> Self {
+        self.lerp(other, amount)
+    }
+
+    /// Hex string of the color, like "#ff8800", with the white value on the end if it has one
+    pub fn to_hex(&self) -> String {
+        self.to_python_color()
+            .iter()
+            .fold("#".to_string(), |hex, value| {
+                format!("{}{:02x}", hex, value)
+            })
+    }
+
+    /// The color as a tuple, like the colors light arrangements return
+    pub fn to_tuple(&self, py: Python) -> PyObject {
+        PythonReturnColor(self.to_python_color()).into_py(py)
+    }
+
+    pub fn __repr__(&self) -> String {
+        match self.w {
+            None => format!("Color({}, {}, {})", self.r, self.g, self.b),
+            Some(w) => format!("Color({}, {}, {}, {})", self.r, self.g, self.b, w),
+        }
+    }
+
+    /// Hashes the red, green and blue values, so colors that are equal hash the same
+    pub fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.r, self.g, self.b).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn __richcmp__(&self, other: &PyAny, op: CompareOp, py: Python) -> PyObject {
+        let other = match other.extract::<PyRef<PyColor>>() {
+            Ok(other) => other,
+            Err(_) => return py.NotImplemented(),
+        };
+        match op {
+            CompareOp::Eq => (*self == *other).into_py(py),
+            CompareOp::Ne => (*self != *other).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+}
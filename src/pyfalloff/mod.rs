@@ -0,0 +1,60 @@
+use pyo3::prelude::*;
+
+use crate::light_arrangement_thread::{Falloff, FalloffCurve};
+use crate::util::to_falloff;
+
+/// How a color set by `set_decreasing_intensity` fades out with distance. Can be passed to it in
+/// place of the name of a curve, to also set how the curve is shaped
+#[pyclass(name = "Falloff", freelist = 16)]
+#[derive(Clone)]
+pub struct PyFalloff {
+    pub falloff: Falloff,
+}
+
+#[pymethods]
+impl PyFalloff {
+    /// `curve` is one of "linear", "quadratic", "inverse-square", "gaussian", "smoothstep" or
+    /// "cosine". `sigma` is the standard deviation of "gaussian" curves, a third of the distance
+    /// they fade over if not given. The color stays at full intensity up to `inner_radius`
+    #[new]
+    #[pyo3(signature = (curve = "linear", sigma = None, inner_radius = 0.0))]
+    pub fn new(curve: &str, sigma: Option<f64>, inner_radius: f64) -> PyResult<Self> {
+        Ok(Self {
+            falloff: to_falloff(curve, sigma, inner_radius)?,
+        })
+    }
+
+    #[getter]
+    pub fn curve(&self) -> &'static str {
+        self.falloff.curve.name()
+    }
+
+    #[getter]
+    pub fn sigma(&self) -> Option<f64> {
+        match self.falloff.curve {
+            FalloffCurve::Gaussian(sigma) => sigma,
+            _ => None,
+        }
+    }
+
+    #[getter]
+    pub fn inner_radius(&self) -> f64 {
+        self.falloff.inner_radius
+    }
+
+    pub fn __repr__(&self) -> String {
+        match self.sigma() {
+            None => format!(
+                "Falloff(\"{}\", inner_radius={})",
+                self.curve(),
+                self.inner_radius()
+            ),
+            Some(sigma) => format!(
+                "Falloff(\"{}\", sigma={}, inner_radius={})",
+                self.curve(),
+                sigma,
+                self.inner_radius()
+            ),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use light_arrangements::{Color, LightStrip};
+
+/// White value of every light on an RGBW strip. `Color` only holds red, green and blue, so the
+/// light arrangement thread keeps the white values here, shared with the strip that shows them
+pub type WhiteChannel = Rc<RefCell<Vec<u8>>>;
+
+/// Creates the white channel for `number_lights` lights, all initially off
+pub fn new_white_channel(number_lights: usize) -> WhiteChannel {
+    Rc::new(RefCell::new(vec![0; number_lights]))
+}
+
+/// Order an RGBW strip expects the values of each light in, such as "grbw" for most SK6812 strips.
+/// Holds the index into [red, green, blue, white] of each value sent
+#[derive(Clone, Copy)]
+pub struct RgbwOrder([usize; 4]);
+
+impl RgbwOrder {
+    /// Parses a 4 character pixel order, returning None if it isn't made of "r", "g", "b" and "w"
+    pub fn parse(pixel_order: &str) -> Option<Self> {
+        let mut order = [0; 4];
+        let mut seen = [false; 4];
+        if pixel_order.chars().count() != 4 {
+            return None;
+        }
+        for (position, channel) in pixel_order.chars().enumerate() {
+            let index = "rgbw".find(channel)?;
+            if seen[index] {
+                return None;
+            }
+            seen[index] = true;
+            order[position] = index;
+        }
+        Some(Self(order))
+    }
+}
+
+/// How an RGBW strip is laid out
+#[derive(Clone, Copy)]
+pub struct RgbwLayout {
+    pub number_lights: i32,
+    pub order: RgbwOrder,
+}
+
+/// Number of 3 value lights a strip needs to send every value of `number_lights` RGBW lights
+pub fn packed_number_lights(number_lights: i32) -> i32 {
+    (number_lights * 4 + 2) / 3
+}
+
+/// Drives an RGBW strip, such as an SK6812, through a strip that sends 3 values per light. The 4
+/// values of every light are packed back to back into the values of the inner strip, which must be
+/// in "rgb" order and have `packed_number_lights` lights
+pub struct PackedRgbwStrip<T: LightStrip> {
+    inner: T,
+    colors: Vec<Color>,
+    white: WhiteChannel,
+    order: RgbwOrder,
+}
+
+impl<T: LightStrip> PackedRgbwStrip<T> {
+    pub fn new(inner: T, white: WhiteChannel, order: RgbwOrder) -> Self {
+        let number_lights = white.borrow().len();
+        Self {
+            inner,
+            colors: vec![
+                Color {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                };
+                number_lights
+            ],
+            white,
+            order,
+        }
+    }
+}
+
+impl<T: LightStrip> LightStrip for PackedRgbwStrip<T> {
+    fn get(&self, index: usize) -> Color {
+        self.colors[index].clone()
+    }
+
+    fn set(&mut self, index: usize, color: &Color) {
+        self.colors[index] = color.clone();
+    }
+
+    fn fill(&mut self, color: &Color) {
+        for light in self.colors.iter_mut() {
+            *light = color.clone();
+        }
+    }
+
+    fn show(&mut self) {
+        let white = self.white.borrow();
+        let mut values = Vec::with_capacity(self.colors.len() * 4 + 2);
+        for (color, white) in self.colors.iter().zip(white.iter()) {
+            let channels = [color.red, color.green, color.blue, *white];
+            values.extend(self.order.0.iter().map(|&channel| channels[channel]));
+        }
+        values.resize(values.len().div_ceil(3) * 3, 0);
+
+        for (index, packed) in values.chunks(3).enumerate() {
+            self.inner.set(
+                index,
+                &Color {
+                    red: packed[0],
+                    green: packed[1],
+                    blue: packed[2],
+                },
+            );
+        }
+        self.inner.show();
+    }
+}
@@ -1,2 +1,18 @@
+use pyo3::types::PyTuple;
+use pyo3::{IntoPy, PyObject, Python};
+
+/// Red, green and blue values of a color, followed by its white value for RGBW strips
 pub type PythonColor = Vec<u8>;
-pub type PythonReturnColor = (u8, u8, u8);
+/// Color sent back to python as a tuple of 3 values, or 4 for RGBW strips
+pub struct PythonReturnColor(pub Vec<u8>);
+/// Colors of every light in order, flattened to [r0, g0, b0, r1, g1, b1, ...], or with the white
+/// value after each color for RGBW strips
+pub type PythonFrame = Vec<u8>;
+/// Index, position and color of every light in a region, in order of index
+pub type PythonSelection = Vec<(usize, Vec<f64>, PythonReturnColor)>;
+
+impl IntoPy<PyObject> for PythonReturnColor {
+    fn into_py(self, py: Python) -> PyObject {
+        PyTuple::new(py, self.0).into()
+    }
+}
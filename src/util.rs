@@ -1,31 +1,280 @@
+use std::time::Duration;
+
 use light_arrangements::Color;
-use pyo3::{exceptions::PyValueError, PyResult};
+use pyo3::exceptions::PyTypeError;
+use pyo3::types::{PyAny, PyBool, PyByteArray, PyList, PyLong, PyString};
+use pyo3::{buffer::PyBuffer, PyObject, PyRef, PyResult, Python};
+
+use crate::errors::{DimensionMismatchError, InvalidColorError, LightArrangementError};
+use crate::light_arrangement_thread::{Blend, BlendMode, Falloff, FalloffCurve, ShutdownPolicy};
+use crate::pycolor::PyColor;
+use crate::pyfalloff::PyFalloff;
+use crate::types::{PythonColor, PythonFrame};
 
 /// Converts a vector to an array
 pub fn vec_to_array<const N: usize>(vec: Vec<f64>) -> PyResult<[f64; N]> {
     if vec.len() == N {
         let vec_len = vec.len();
-        return match vec.try_into() {
+        match vec.try_into() {
             Ok(array) => Ok(array),
-            Err(_) => Err(PyValueError::new_err(format!(
+            Err(_) => Err(DimensionMismatchError::new_err(format!(
                 "expected a vec of length {} but it was {}",
                 N, vec_len,
             ))),
-        };
+        }
     } else {
-        return Err(PyValueError::new_err(format!(
+        Err(DimensionMismatchError::new_err(format!(
             "Expected input of dimension {} but was {}",
             N,
             vec.len()
+        )))
+    }
+}
+
+/// Converts a python color into its red, green and blue values, followed by its white value if it
+/// has one. Accepts a `Color`, a sequence of 3 or 4 values, a hex string like "#ff8800" or "#ff880040", an int
+/// like 0xff8800, or an object with `r`, `g` and `b` attributes and optionally `w`
+pub fn to_color(color: &PyAny) -> PyResult<PythonColor> {
+    if let Ok(color) = color.extract::<PyRef<PyColor>>() {
+        return Ok(color.to_python_color());
+    }
+    if let Ok(hex) = color.downcast::<PyString>() {
+        return hex_to_color(hex.to_str()?);
+    }
+    // Bools are ints in python, but are never meant as a color
+    if color.is_instance_of::<PyBool>()? {
+        return Err(InvalidColorError::new_err(format!(
+            "Color should not be a bool but was {}",
+            color
         )));
     }
+    if color.is_instance_of::<PyLong>()? {
+        let value = color.extract::<i64>()?;
+        if !(0..=0xffffff).contains(&value) {
+            return Err(InvalidColorError::new_err(format!(
+                "Color int {:#x} should be between 0x000000 and 0xffffff",
+                value
+            )));
+        }
+        return Ok(vec![(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+    }
+    if color.hasattr("r")? && color.hasattr("g")? && color.hasattr("b")? {
+        let mut values = vec![
+            color_attribute(color, "r")?,
+            color_attribute(color, "g")?,
+            color_attribute(color, "b")?,
+        ];
+        if color.hasattr("w")? {
+            values.push(color_attribute(color, "w")?);
+        }
+        return Ok(values);
+    }
+
+    let values = color.extract::<Vec<u8>>()?;
+    if values.len() != 3 && values.len() != 4 {
+        return Err(InvalidColorError::new_err(format!(
+            "Color should have 3 values (red, green, blue) or 4 (red, green, blue, white) but had {}",
+            values.len()
+        )));
+    }
+    Ok(values)
+}
+
+/// Reads the value in attribute `name` of a color object, which should be between 0 and 255
+fn color_attribute(color: &PyAny, name: &str) -> PyResult<u8> {
+    let value = color.getattr(name)?;
+    value.extract().map_err(|_| {
+        InvalidColorError::new_err(format!(
+            "Color attribute {} should be between 0 and 255 but was {}",
+            name, value
+        ))
+    })
+}
+
+/// Converts a hex string like "#ff8800" into its red, green and blue values, or "#ff880040" into
+/// its red, green, blue and white values
+pub fn hex_to_color(hex: &str) -> PyResult<PythonColor> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if (digits.len() != 6 && digits.len() != 8)
+        || !digits.chars().all(|digit| digit.is_ascii_hexdigit())
+    {
+        return Err(InvalidColorError::new_err(format!(
+            "Color string \"{}\" should be a hex color like \"#ff8800\"",
+            hex
+        )));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|start| {
+            u8::from_str_radix(&digits[start..start + 2], 16)
+                .map_err(|error| InvalidColorError::new_err(error.to_string()))
+        })
+        .collect()
+}
+
+/// Converts red, green and blue values to red, green, blue and white values, moving the white that
+/// all three share into the white value
+pub fn extract_white(color: &[u8]) -> PythonColor {
+    let white = color[0].min(color[1]).min(color[2]);
+    vec![color[0] - white, color[1] - white, color[2] - white, white]
 }
 
 /// Converts the first 3 elements of a vector to a Color
-pub fn vec_to_color(vec: &Vec<u8>) -> Color {
+pub fn vec_to_color(vec: &[u8]) -> Color {
     Color {
         red: vec[0],
         green: vec[1],
         blue: vec[2],
     }
 }
+
+/// Copies a (number_lights, channels) buffer of colors, such as a uint8 numpy array, into a flat
+/// frame
+pub fn buffer_to_frame(
+    py: Python,
+    buffer: &PyBuffer<u8>,
+    number_lights: usize,
+    channels: usize,
+) -> PyResult<PythonFrame> {
+    let shape = buffer.shape();
+    if shape.len() == 2 && shape[1] != channels {
+        return Err(DimensionMismatchError::new_err(format!(
+            "Expected colors of shape ({}, {}) but was ({}, {})",
+            number_lights, channels, shape[0], shape[1]
+        )));
+    }
+    if buffer.item_count() != number_lights * channels {
+        return Err(DimensionMismatchError::new_err(format!(
+            "Expected {} color values for {} lights but got {}",
+            number_lights * channels,
+            number_lights,
+            buffer.item_count()
+        )));
+    }
+    buffer.to_vec(py)
+}
+
+/// Converts a flat frame into a (number_lights, channels) uint8 numpy array
+pub fn frame_to_numpy(py: Python, frame: PythonFrame, channels: usize) -> PyResult<PyObject> {
+    let number_lights = frame.len() / channels;
+    let array = py
+        .import("numpy")?
+        .call_method1("frombuffer", (PyByteArray::new(py, &frame), "uint8"))?
+        .call_method1("reshape", ((number_lights, channels),))?;
+    Ok(array.into())
+}
+
+/// Converts flattened light positions into a (number_lights, dimensions) float64 numpy array
+pub fn positions_to_numpy(
+    py: Python,
+    positions: Vec<f64>,
+    dimensions: usize,
+) -> PyResult<PyObject> {
+    let number_lights = positions.len() / dimensions;
+    let array = py
+        .import("numpy")?
+        .call_method1("array", (PyList::new(py, positions), "float64"))?
+        .call_method1("reshape", ((number_lights, dimensions),))?;
+    Ok(array.into())
+}
+
+/// Converts "blank", "keep" or a color into the matching shutdown policy
+pub fn to_shutdown_policy(policy: &PyAny) -> PyResult<ShutdownPolicy> {
+    if let Ok(name) = policy.extract::<String>() {
+        return match name.as_str() {
+            "blank" => Ok(ShutdownPolicy::Blank),
+            "keep" => Ok(ShutdownPolicy::Keep),
+            _ => hex_to_color(&name).map(ShutdownPolicy::Color).map_err(|_| {
+                LightArrangementError::new_err(format!(
+                    "Shutdown policy \"{}\" should be \"blank\", \"keep\" or a color",
+                    name
+                ))
+            }),
+        };
+    }
+    Ok(ShutdownPolicy::Color(to_color(policy)?))
+}
+
+/// Converts the name of a blend mode and the opacity it is drawn with into a blend
+pub fn to_blend(mode: &str, opacity: f64) -> PyResult<Blend> {
+    let mode = BlendMode::parse(mode).ok_or_else(|| {
+        LightArrangementError::new_err(format!(
+            "Blend mode \"{}\" should be one of \"replace\", \"add\", \"max\", \"min\", \"multiply\", \"screen\" or \"alpha\"",
+            mode
+        ))
+    })?;
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(LightArrangementError::new_err(format!(
+            "Opacity should be between 0 and 1 but was {}",
+            opacity
+        )));
+    }
+    Ok(Blend { mode, opacity })
+}
+
+/// Converts the name of a falloff curve, the standard deviation of gaussian curves and the inner
+/// radius kept at full intensity into a falloff
+pub fn to_falloff(curve: &str, sigma: Option<f64>, inner_radius: f64) -> PyResult<Falloff> {
+    if !inner_radius.is_finite() || inner_radius < 0.0 {
+        return Err(LightArrangementError::new_err(format!(
+            "Inner radius should be a positive number but was {}",
+            inner_radius
+        )));
+    }
+    if let Some(sigma) = sigma {
+        if !sigma.is_finite() || sigma <= 0.0 {
+            return Err(LightArrangementError::new_err(format!(
+                "Sigma should be a positive number but was {}",
+                sigma
+            )));
+        }
+    }
+    let curve = FalloffCurve::parse(curve, sigma).ok_or_else(|| {
+        LightArrangementError::new_err(format!(
+            "Falloff \"{}\" should be one of \"linear\", \"quadratic\", \"inverse-square\", \"gaussian\", \"smoothstep\" or \"cosine\"",
+            curve
+        ))
+    })?;
+    if sigma.is_some() && !matches!(curve, FalloffCurve::Gaussian(_)) {
+        return Err(LightArrangementError::new_err(format!(
+            "Sigma is only used by the \"gaussian\" falloff, not \"{}\"",
+            curve.name()
+        )));
+    }
+    Ok(Falloff {
+        curve,
+        inner_radius,
+    })
+}
+
+/// Converts a python falloff, either a `Falloff` or the name of a curve, into a falloff. None is
+/// returned if it isn't given, leaving the light arrangement's own falloff
+pub fn to_falloff_arg(falloff: Option<&PyAny>) -> PyResult<Option<Falloff>> {
+    let falloff = match falloff {
+        Some(falloff) => falloff,
+        None => return Ok(None),
+    };
+    if let Ok(falloff) = falloff.extract::<PyRef<PyFalloff>>() {
+        return Ok(Some(falloff.falloff));
+    }
+    if let Ok(curve) = falloff.downcast::<PyString>() {
+        return to_falloff(curve.to_str()?, None, 0.0).map(Some);
+    }
+    Err(PyTypeError::new_err(
+        "Falloff should be a Falloff or the name of a falloff curve",
+    ))
+}
+
+/// Converts a timeout in seconds into a Duration, where None means no timeout
+pub fn to_timeout(seconds: Option<f64>) -> PyResult<Option<Duration>> {
+    match seconds {
+        None => Ok(None),
+        Some(seconds) if seconds > 0.0 && seconds.is_finite() => {
+            Ok(Some(Duration::from_secs_f64(seconds)))
+        }
+        Some(seconds) => Err(LightArrangementError::new_err(format!(
+            "Timeout should be a positive number of seconds or None but was {}",
+            seconds
+        ))),
+    }
+}
@@ -1,24 +1,39 @@
 #!/usr/bin/env python3
 
 import light_arrangements_python
+import threading
 import unittest
 import numpy as np
 
 NUMBER_LIGHTS = 100
 
 
+def on_raspberry_pi():
+    try:
+        with open("/proc/device-tree/model") as model:
+            return "Raspberry Pi" in model.read()
+    except OSError:
+        return False
+
+
 def get_light_arrangements():
-    return light_arrangements_python.init_test(
-        2, "./tests/positions2d.csv", 10, 0.03, (0.5, 0.5, 2), [0, 1, 2]
-    )
+    return light_arrangements_python.init_headless(2, "./tests/positions2d.csv", 10)
 
 
 class TestWs281xConstructor(unittest.TestCase):
+    @unittest.skipUnless(on_raspberry_pi(), "ws281x strips need a Raspberry Pi")
     def test_construct_ws281x(self):
         arr = light_arrangements_python.init_ws281x(
             2, "./tests/positions2d.csv", 10, 100, 18, 125, "rgb", 800000
         )
 
+    @unittest.skipUnless(on_raspberry_pi(), "ws281x strips need a Raspberry Pi")
+    def test_construct_ws281x_rgbw(self):
+        arr = light_arrangements_python.init_ws281x(
+            2, "./tests/positions2d.csv", 10, 100, 18, 125, "grbw", 800000
+        )
+        self.assertEqual(arr.channels, 4)
+
     def test_construct_ws281x_bad_rgb(self):
         with self.assertRaises(ValueError):
             light_arrangements_python.init_ws281x(
@@ -31,6 +46,48 @@ class TestWs281xConstructor(unittest.TestCase):
                 2, "./tests/positions2d.csv", 10, 100, 18, 125, "rgbg", 800000
             )
 
+    def test_construct_ws281x_failure_raises(self):
+        with self.assertRaises(ValueError):
+            light_arrangements_python.init_ws281x(
+                2, "./tests/does_not_exist.csv", 10, 100, 18, 125, "rgb", 800000
+            )
+
+
+class TestHeadlessConstructor(unittest.TestCase):
+    def test_construct_headless(self):
+        arr = light_arrangements_python.init_headless(2, "./tests/positions2d.csv", 10)
+        self.assertEqual(arr.number_lights(), NUMBER_LIGHTS)
+
+    def test_construct_headless_bad_dimension(self):
+        with self.assertRaises(ValueError):
+            light_arrangements_python.init_headless(5, "./tests/positions2d.csv", 10)
+
+    def test_unknown_option_rejected(self):
+        with self.assertRaises(TypeError):
+            light_arrangements_python.init_headless(
+                2, "./tests/positions2d.csv", 10, max_miliamps=500
+            )
+        with self.assertRaises(TypeError):
+            light_arrangements_python.init_headless(
+                2, "./tests/positions2d.csv", 10, True
+            )
+        with self.assertRaises(TypeError):
+            light_arrangements_python.init_ws281x(
+                2,
+                "./tests/positions2d.csv",
+                10,
+                100,
+                18,
+                125,
+                "rgb",
+                800000,
+                rgbw=True,
+            )
+
+    def test_construct_headless_bad_file(self):
+        with self.assertRaises(ValueError):
+            light_arrangements_python.init_headless(2, "./tests/does_not_exist.csv", 10)
+
 
 class TestFill(unittest.TestCase):
     def test_fill(self):
@@ -97,6 +154,132 @@ class SetDecreasingIntensity(unittest.TestCase):
         self.assertTrue(arr.get_closest([0.5, 0.5], 0.2)[0] != 0)
 
 
+class Falloff(unittest.TestCase):
+    def faded_red(self, falloff=None, **kwargs):
+        if kwargs:
+            falloff = light_arrangements_python.Falloff(falloff or "linear", **kwargs)
+        arr = get_light_arrangements()
+        arr.set_decreasing_intensity([0.0, 0.0], 0.3, (200, 0, 0), falloff=falloff)
+        self.assertEqual(arr.get_by_index(0), (200, 0, 0))
+        self.assertEqual(arr.get_by_index(4), (0, 0, 0))
+        return arr.get_by_index(1)[0]
+
+    def test_falloff_curves(self):
+        linear = self.faded_red(falloff="linear")
+        self.assertEqual(linear, 126)
+        self.assertLess(self.faded_red(falloff="quadratic"), linear)
+        self.assertLess(self.faded_red(falloff="inverse-square"), linear)
+        self.assertGreater(self.faded_red(falloff="smoothstep"), linear)
+        self.assertGreater(self.faded_red(falloff="cosine"), linear)
+        self.assertLess(
+            self.faded_red(falloff="gaussian", sigma=0.05),
+            self.faded_red(falloff="gaussian", sigma=0.2),
+        )
+
+    def test_inner_radius(self):
+        self.assertEqual(self.faded_red(inner_radius=0.2), 200)
+
+    def test_falloff_object(self):
+        falloff = light_arrangements_python.Falloff(
+            "gaussian", sigma=0.1, inner_radius=0.05
+        )
+        self.assertEqual(falloff.curve, "gaussian")
+        self.assertEqual(falloff.sigma, 0.1)
+        self.assertEqual(falloff.inner_radius, 0.05)
+        cosine = light_arrangements_python.Falloff("cosine")
+        self.assertEqual(cosine.sigma, None)
+        self.assertEqual(
+            self.faded_red(falloff="cosine"), self.faded_red(falloff=cosine)
+        )
+
+    def test_falloff_merge(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(1, (150, 150, 150))
+        arr.set_decreasing_intensity_merge(
+            [0.0, 0.0], 0.3, (200, 0, 0), falloff="linear"
+        )
+        self.assertEqual(arr.get_by_index(0), (200, 0, 0))
+        self.assertEqual(arr.get_by_index(1), (150, 150, 150))
+
+    def test_bad_falloff(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_decreasing_intensity([0.0, 0.0], 0.3, (1, 2, 3), falloff="cubic")
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            light_arrangements_python.Falloff(inner_radius=-1)
+        with self.assertRaises(ValueError):
+            light_arrangements_python.Falloff("linear", sigma=0.1)
+        with self.assertRaises(ValueError):
+            light_arrangements_python.Falloff("gaussian", sigma=0)
+        with self.assertRaises(TypeError):
+            arr.set_decreasing_intensity([0.0, 0.0], 0.3, (1, 2, 3), falloff=0.1)
+
+
+class SetAll(unittest.TestCase):
+    def test_set_all_numpy(self):
+        arr = get_light_arrangements()
+        colors = np.zeros((NUMBER_LIGHTS, 3), dtype=np.uint8)
+        colors[:, 0] = 255
+        colors[5] = (1, 2, 3)
+        arr.set_all(colors)
+        self.assertEqual(arr.get_by_index(0), (255, 0, 0))
+        self.assertEqual(arr.get_by_index(5), (1, 2, 3))
+
+    def test_set_all_buffer(self):
+        arr = get_light_arrangements()
+        arr.set_all(bytes([10, 20, 30]) * NUMBER_LIGHTS)
+        for i in range(NUMBER_LIGHTS):
+            self.assertEqual(arr.get_by_index(i), (10, 20, 30))
+
+    def test_set_all_wrong_shape(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.DimensionMismatchError):
+            arr.set_all(np.zeros((NUMBER_LIGHTS, 4), dtype=np.uint8))
+        with self.assertRaises(light_arrangements_python.DimensionMismatchError):
+            arr.set_all(np.zeros((NUMBER_LIGHTS - 1, 3), dtype=np.uint8))
+
+    def test_get_all(self):
+        arr = get_light_arrangements()
+        arr.fill((1, 2, 3))
+        arr.set_by_index(7, (9, 8, 7))
+        colors = arr.get_all()
+        self.assertEqual(colors.shape, (NUMBER_LIGHTS, 3))
+        self.assertEqual(colors.dtype, np.uint8)
+        self.assertEqual(tuple(colors[7]), (9, 8, 7))
+        self.assertEqual(tuple(colors[0]), (1, 2, 3))
+
+    def test_get_all_round_trip(self):
+        arr = get_light_arrangements()
+        colors = np.arange(NUMBER_LIGHTS * 3, dtype=np.uint8).reshape((NUMBER_LIGHTS, 3))
+        arr.set_all(colors)
+        self.assertTrue(np.array_equal(arr.get_all(), colors))
+
+    def test_set_all_wrong_type(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(BufferError):
+            arr.set_all(np.zeros((NUMBER_LIGHTS, 3), dtype=np.float64))
+
+
+class Positions(unittest.TestCase):
+    def test_positions(self):
+        arr = get_light_arrangements()
+        positions = arr.positions()
+        self.assertEqual(positions.shape, (NUMBER_LIGHTS, 2))
+        self.assertEqual(positions.dtype, np.float64)
+        self.assertEqual(tuple(positions[0]), (0.0, 0.0))
+
+    def test_position_of(self):
+        arr = get_light_arrangements()
+        positions = arr.positions()
+        for i in range(NUMBER_LIGHTS):
+            self.assertEqual(arr.position_of(i), list(positions[i]))
+
+    def test_position_of_bad_index(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.IndexOutOfRangeError):
+            arr.position_of(NUMBER_LIGHTS)
+
+
 class SetBox(unittest.TestCase):
     def test_box(self):
         arr = get_light_arrangements()
@@ -113,6 +296,66 @@ class SetRadius(unittest.TestCase):
         self.assertTrue(arr.get_closest([0.5, 0.5], 0.2)[0] == 255)
 
 
+class SpatialReads(unittest.TestCase):
+    def test_get_all_in_box(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(1, (1, 2, 3))
+        lights = arr.get_all_in_box([0.0, 0.0], [0.05, 0.2])
+        self.assertEqual([index for index, _, _ in lights], [0, 1])
+        self.assertEqual(lights[1], (1, [0.0, 0.1111111111111111], (1, 2, 3)))
+
+    def test_get_all_in_radius(self):
+        arr = get_light_arrangements()
+        arr.fill((4, 5, 6))
+        lights = arr.get_all_in_radius([0.0, 0.0], 0.12)
+        self.assertEqual(len(lights), 3)
+        for index, position, color in lights:
+            self.assertEqual(position, arr.position_of(index))
+            self.assertEqual(color, (4, 5, 6))
+
+    def test_indices(self):
+        arr = get_light_arrangements()
+        self.assertEqual(arr.indices_in_box([0.0, 0.0], [0.05, 0.2]), [0, 1])
+        self.assertEqual(arr.indices_in_radius([0.0, 0.0], 0.05), [0])
+        self.assertEqual(arr.indices_in_radius([10.0, 10.0], 0.05), [])
+
+    def test_matches_setters(self):
+        arr = get_light_arrangements()
+        arr.set_all_in_radius([0.5, 0.5], 0.3, (255, 0, 0))
+        for index in range(NUMBER_LIGHTS):
+            lit = arr.get_by_index(index) == (255, 0, 0)
+            self.assertEqual(lit, index in arr.indices_in_radius([0.5, 0.5], 0.3))
+
+    def test_box_matches_setters(self):
+        # Both corners are on lights, so lights on every edge of the box are included
+        corner1 = [0.0, 0.0]
+        corner2 = [0.1111111111111111, 0.2222222222222222]
+        arr = get_light_arrangements()
+        indices = arr.indices_in_box(corner1, corner2)
+        self.assertEqual(indices, [0, 1, 2, 10, 11, 12])
+        arr.set_all_in_box(corner1, corner2, (255, 0, 0))
+        lit = [i for i in range(NUMBER_LIGHTS) if arr.get_by_index(i) == (255, 0, 0)]
+        self.assertEqual(lit, indices)
+
+        arr = get_light_arrangements()
+        arr.set_all_in_box(corner1, corner2, (255, 0, 0), blend="add")
+        lit = [i for i in range(NUMBER_LIGHTS) if arr.get_by_index(i) == (255, 0, 0)]
+        self.assertEqual(lit, indices)
+
+    def test_reads_change_no_lights(self):
+        arr = get_light_arrangements()
+        arr.fill((1, 2, 3))
+        arr.get_all_in_box([0.0, 0.0], [1.0, 1.0])
+        arr.get_all_in_radius([0.5, 0.5], 0.3)
+        for index in range(NUMBER_LIGHTS):
+            self.assertEqual(arr.get_by_index(index), (1, 2, 3))
+
+    def test_bad_dimension(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.DimensionMismatchError):
+            arr.get_all_in_radius([0.0, 0.0, 0.0], 0.1)
+
+
 class ColorTypes(unittest.TestCase):
     def test_accepts_lists_for_colors(self):
         arr = get_light_arrangements()
@@ -135,7 +378,176 @@ class ColorTypes(unittest.TestCase):
         arr.set_all_in_box((0.5, 0.5), (0.7, 0.7), color)
 
 
+class ColorFormats(unittest.TestCase):
+    def test_hex_string(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(0, "#ff8800")
+        self.assertEqual(arr.get_by_index(0), (255, 136, 0))
+        arr.set_by_index(0, "0A0b0C")
+        self.assertEqual(arr.get_by_index(0), (10, 11, 12))
+
+    def test_int(self):
+        arr = get_light_arrangements()
+        arr.fill(0xFF8800)
+        self.assertEqual(arr.get_by_index(4), (255, 136, 0))
+
+    def test_bool_rejected(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.fill(True)
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.set_by_index(0, False)
+
+    def test_rgb_attributes(self):
+        class Rgb:
+            r = 1
+            g = 2
+            b = 3
+
+        arr = get_light_arrangements()
+        arr.set_by_index(1, Rgb())
+        self.assertEqual(arr.get_by_index(1), (1, 2, 3))
+
+    def test_bad_rgb_attributes(self):
+        class TooRed:
+            r = 300
+            g = 2
+            b = 3
+
+        class NamedGreen:
+            r = 1
+            g = "green"
+            b = 3
+
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.set_by_index(1, TooRed())
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.set_by_index(1, NamedGreen())
+
+
+class ColorClass(unittest.TestCase):
+    def test_constructors(self):
+        Color = light_arrangements_python.Color
+        self.assertEqual(Color.rgb(1, 2, 3).to_tuple(), (1, 2, 3))
+        self.assertEqual(Color.hex("#ff8800"), Color(255, 136, 0))
+        self.assertEqual(Color.hsv(120, 1, 1), Color(0, 255, 0))
+        self.assertEqual(Color.hsl(240, 1, 0.5), Color(0, 0, 255))
+        self.assertEqual(Color.kelvin(6600), Color(255, 255, 255))
+        warm = Color.kelvin(2700)
+        self.assertTrue(warm.r > warm.g > warm.b)
+
+    def test_lerp_and_mix(self):
+        Color = light_arrangements_python.Color
+        red = Color(255, 0, 0)
+        blue = Color(0, 0, 255)
+        self.assertEqual(red.lerp(blue, 0), red)
+        self.assertEqual(red.lerp(blue, 1), blue)
+        self.assertEqual(red.mix(blue), red.lerp(blue, 0.5))
+        gray = Color(0, 0, 0).mix(Color(255, 255, 255))
+        self.assertEqual(gray.r, gray.g)
+        self.assertEqual(gray.g, gray.b)
+
+    def test_setters_accept_color(self):
+        arr = get_light_arrangements()
+        color = light_arrangements_python.Color.hsv(0, 1, 1)
+        arr.fill(color)
+        self.assertEqual(arr.get_by_index(0), (255, 0, 0))
+        arr.set_by_index(1, light_arrangements_python.Color(1, 2, 3))
+        self.assertEqual(arr.get_by_index(1), (1, 2, 3))
+        arr.set_all_in_radius([0.5, 0.5], 0.2, color)
+
+    def test_hash_matches_equality(self):
+        Color = light_arrangements_python.Color
+        self.assertEqual(hash(Color(1, 2, 3)), hash(Color.hex("#010203")))
+        self.assertEqual(len({Color(1, 2, 3), Color(1, 2, 3), Color(3, 2, 1)}), 2)
+
+
+class Rgbw(unittest.TestCase):
+    def get_rgbw_arrangement(self):
+        return light_arrangements_python.init_headless(
+            2, "./tests/positions2d.csv", 10, rgbw=True
+        )
+
+    def test_channels(self):
+        self.assertEqual(get_light_arrangements().channels, 3)
+        self.assertEqual(self.get_rgbw_arrangement().channels, 4)
+
+    def test_set_and_get_white(self):
+        arr = self.get_rgbw_arrangement()
+        arr.set_by_index(2, (1, 2, 3, 4))
+        self.assertEqual(arr.get_by_index(2), (1, 2, 3, 4))
+        arr.fill("#01020304")
+        self.assertEqual(arr.get_by_index(9), (1, 2, 3, 4))
+
+    def test_white_follows_location_setters(self):
+        arr = self.get_rgbw_arrangement()
+        arr.set_all_in_radius([0.5, 0.5], 0.5, (0, 0, 0, 200))
+        self.assertEqual(arr.get_closest([0.5, 0.5], 0.2), (0, 0, 0, 200))
+        arr.set_all_in_box([0, 0], [1, 1], (0, 0, 0, 100))
+        self.assertEqual(arr.get_closest([0.5, 0.5], 0.2), (0, 0, 0, 100))
+
+    def test_white_set_on_same_lights_as_colors(self):
+        arr = self.get_rgbw_arrangement()
+        arr.set_decreasing_intensity([0.5, 0.5], 0.4, (200, 200, 200, 200))
+        arr.set_decreasing_intensity_merge([0.2, 0.3], 0.3, (150, 150, 150, 150))
+        arr.set_all_in_box([0.6, 0.0], [0.9, 0.2], (90, 90, 90, 90))
+        arr.set_closest([0.95, 0.95], 0.2, (30, 30, 30, 30))
+        for index in range(NUMBER_LIGHTS):
+            r, g, b, w = arr.get_by_index(index)
+            self.assertEqual(w, r)
+        self.assertEqual(arr.get_closest([0.95, 0.95], 0.2), (30, 30, 30, 30))
+
+    def test_rgb_colors_get_no_white_by_default(self):
+        arr = self.get_rgbw_arrangement()
+        arr.set_by_index(0, (10, 20, 30))
+        self.assertEqual(arr.get_by_index(0), (10, 20, 30, 0))
+
+    def test_extract_white(self):
+        arr = self.get_rgbw_arrangement()
+        self.assertFalse(arr.extract_white)
+        arr.extract_white = True
+        arr.set_by_index(0, (10, 20, 30))
+        self.assertEqual(arr.get_by_index(0), (0, 10, 20, 10))
+
+    def test_set_all_bytes(self):
+        arr = self.get_rgbw_arrangement()
+        arr.set_all(bytes([1, 2, 3, 4]) * NUMBER_LIGHTS)
+        self.assertEqual(arr.get_by_index(50), (1, 2, 3, 4))
+        with self.assertRaises(light_arrangements_python.DimensionMismatchError):
+            arr.set_all(bytes([1, 2, 3]) * NUMBER_LIGHTS)
+
+    def test_white_rejected_without_white_channel(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.set_by_index(0, (1, 2, 3, 4))
+
+    def test_bad_rgbw_pixel_order(self):
+        with self.assertRaises(ValueError):
+            light_arrangements_python.init_ws281x(
+                2, "./tests/positions2d.csv", 10, 100, 18, 125, "grbb", 800000
+            )
+
+
 class BadColor(unittest.TestCase):
+    def test_wrong_number_of_values(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.set_by_index(0, (255,))
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.set_by_index(0, (1, 2, 3, 4, 5))
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.set_by_index(0, (1, 2))
+
+    def test_bad_hex_and_int(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.fill("#ff88")
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.fill("orange")
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.fill(0x1000000)
+
     def test_too_big_colors(self):
         arr = get_light_arrangements()
         with self.assertRaises(OverflowError):
@@ -156,6 +568,55 @@ class BadColor(unittest.TestCase):
             arr.set_by_index(0, (2.5, 3.5, 0.334))
 
 
+class Blend(unittest.TestCase):
+    def test_blend_modes(self):
+        expected = {
+            "replace": (50, 200, 0),
+            "add": (150, 255, 100),
+            "max": (100, 200, 100),
+            "min": (50, 100, 0),
+            "multiply": (20, 78, 0),
+            "screen": (130, 222, 100),
+            "alpha": (50, 200, 0),
+        }
+        arr = get_light_arrangements()
+        for mode, color in expected.items():
+            arr.set_by_index(0, (100, 100, 100))
+            arr.set_by_index(0, (50, 200, 0), blend=mode)
+            self.assertEqual(arr.get_by_index(0), color, mode)
+
+    def test_opacity(self):
+        arr = get_light_arrangements()
+        arr.fill((0, 0, 200))
+        arr.fill((200, 0, 0), blend="alpha", opacity=0.25)
+        self.assertEqual(arr.get_by_index(5), (50, 0, 150))
+
+    def test_blend_only_changes_selected_lights(self):
+        arr = get_light_arrangements()
+        arr.fill((10, 10, 10))
+        arr.set_all_in_radius([0.5, 0.5], 0.1, (10, 20, 30), blend="add")
+        self.assertEqual(arr.get_closest([0.5, 0.5], 0.1), (20, 30, 40))
+        arr.set_all_in_box([0.0, 0.0], [1, 1], (5, 5, 5), blend="max")
+        self.assertEqual(arr.get_closest([0.5, 0.5], 0.1), (20, 30, 40))
+        arr.set_closest([0.5, 0.5], 0.1, (0, 0, 0), blend="min")
+        self.assertEqual(arr.get_closest([0.5, 0.5], 0.1), (0, 0, 0))
+
+    def test_rgbw_blend(self):
+        arr = light_arrangements_python.init_headless(
+            2, "./tests/positions2d.csv", 10, rgbw=True
+        )
+        arr.set_by_index(0, (10, 20, 30, 40))
+        arr.set_by_index(0, (1, 2, 3, 4), blend="add")
+        self.assertEqual(arr.get_by_index(0), (11, 22, 33, 44))
+
+    def test_bad_blend(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.fill((1, 2, 3), blend="overlay")
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.fill((1, 2, 3), blend="alpha", opacity=2)
+
+
 class BadDimensions(unittest.TestCase):
     def test_mixed_dimensions(self):
         arr = get_light_arrangements()
@@ -168,12 +629,579 @@ class BadDimensions(unittest.TestCase):
             arr.set_closest([], 0.0, color)
 
 
+class Frame(unittest.TestCase):
+    def test_frame_sends_draws_on_exit(self):
+        arr = get_light_arrangements()
+        with arr.frame() as frame:
+            frame.fill((1, 1, 1))
+            frame.set_by_index(3, (5, 5, 5))
+            self.assertEqual(arr.get_by_index(3), (0, 0, 0))
+        self.assertEqual(arr.get_by_index(3), (5, 5, 5))
+        self.assertEqual(arr.get_by_index(0), (1, 1, 1))
+
+    def test_frame_without_show(self):
+        arr = get_light_arrangements()
+        with arr.frame(show=False):
+            arr.set_all_in_radius([0.5, 0.5], 0.5, (255, 255, 255))
+        self.assertEqual(arr.get_closest([0.5, 0.5], 0.2), (255, 255, 255))
+
+    def test_frame_error_raised_on_exit(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.IndexOutOfRangeError):
+            with arr.frame():
+                arr.set_by_index(NUMBER_LIGHTS, (1, 1, 1))
+
+    def test_failed_frame_changes_no_lights(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.IndexOutOfRangeError):
+            with arr.frame():
+                arr.set_by_index(2, (3, 3, 3))
+                arr.set_by_index(NUMBER_LIGHTS, (1, 1, 1))
+        self.assertEqual(arr.get_by_index(2), (0, 0, 0))
+
+    def test_frame_only_collects_draws_from_its_thread(self):
+        arr = get_light_arrangements()
+        with arr.frame():
+            arr.set_by_index(6, (6, 6, 6))
+            thread = threading.Thread(target=arr.set_by_index, args=(7, (7, 7, 7)))
+            thread.start()
+            thread.join()
+            self.assertEqual(arr.get_by_index(7), (7, 7, 7))
+            self.assertEqual(arr.get_by_index(6), (0, 0, 0))
+        self.assertEqual(arr.get_by_index(6), (6, 6, 6))
+
+    def test_frame_discarded_on_exception(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(KeyError):
+            with arr.frame():
+                arr.set_by_index(4, (2, 2, 2))
+                raise KeyError()
+        self.assertEqual(arr.get_by_index(4), (0, 0, 0))
+
+    def test_nested_frames(self):
+        arr = get_light_arrangements()
+        with arr.frame():
+            with self.assertRaises(light_arrangements_python.LightArrangementError):
+                with arr.frame():
+                    pass
+
+
+class Async(unittest.TestCase):
+    def test_async_draws_are_applied(self):
+        arr = get_light_arrangements()
+        arr.set_async(True)
+        arr.fill((1, 1, 1))
+        arr.set_by_index(3, (4, 4, 4))
+        arr.set_all_in_box([0.0, 0.0], [0.1, 0.1], (2, 2, 2))
+        self.assertEqual(arr.get_by_index(3), (4, 4, 4))
+        self.assertEqual(arr.get_by_index(50), (1, 1, 1))
+        arr.flush()
+
+    def test_async_error_raised_on_flush(self):
+        arr = get_light_arrangements()
+        arr.set_async(True)
+        arr.set_by_index(NUMBER_LIGHTS, (1, 1, 1))
+        with self.assertRaises(light_arrangements_python.IndexOutOfRangeError):
+            arr.flush()
+        arr.flush()
+
+    def test_async_error_raised_on_show(self):
+        arr = get_light_arrangements()
+        arr.set_async(True)
+        arr.set_by_index(NUMBER_LIGHTS, (1, 1, 1))
+        with self.assertRaises(light_arrangements_python.IndexOutOfRangeError):
+            arr.show()
+        arr.show()
+
+    def test_async_off_waits_again(self):
+        arr = get_light_arrangements()
+        arr.set_async(True)
+        arr.set_async(False)
+        with self.assertRaises(light_arrangements_python.IndexOutOfRangeError):
+            arr.set_by_index(NUMBER_LIGHTS, (1, 1, 1))
+
+
+class Close(unittest.TestCase):
+    def test_close(self):
+        arr = get_light_arrangements()
+        self.assertFalse(arr.is_closed)
+        arr.close()
+        self.assertTrue(arr.is_closed)
+        arr.close()
+
+    def test_calls_after_close_raise(self):
+        arr = get_light_arrangements()
+        arr.close()
+        with self.assertRaises(light_arrangements_python.ArrangementClosedError):
+            arr.get_by_index(0)
+        with self.assertRaises(light_arrangements_python.ArrangementClosedError):
+            arr.fill((1, 1, 1))
+        with self.assertRaises(light_arrangements_python.ArrangementClosedError):
+            arr.show()
+
+    def test_settings_after_close_raise(self):
+        arr = get_light_arrangements()
+        arr.close()
+        with self.assertRaises(light_arrangements_python.ArrangementClosedError):
+            arr.set_async(True)
+        with self.assertRaises(light_arrangements_python.ArrangementClosedError):
+            arr.extract_white = True
+        with self.assertRaises(light_arrangements_python.ArrangementClosedError):
+            arr.timeout = 1.0
+
+    def test_context_manager(self):
+        with get_light_arrangements() as arr:
+            arr.fill((1, 1, 1))
+            self.assertFalse(arr.is_closed)
+        self.assertTrue(arr.is_closed)
+
+
+class ShutdownPolicy(unittest.TestCase):
+    def test_shutdown_policies(self):
+        for policy, final_color in [
+            ("blank", (0, 0, 0)),
+            ("keep", (100, 100, 100)),
+            ((10, 20, 30), (10, 20, 30)),
+            ([1, 2, 3], (1, 2, 3)),
+        ]:
+            arr = get_light_arrangements()
+            arr.set_shutdown_policy(policy)
+            arr.fill((100, 100, 100))
+            arr.show()
+            arr.close()
+            self.assertEqual(arr.shown_frame(), [final_color] * NUMBER_LIGHTS)
+
+    def test_keep_leaves_last_shown_frame(self):
+        arr = get_light_arrangements()
+        arr.set_shutdown_policy("keep")
+        arr.fill((100, 100, 100))
+        arr.show()
+        arr.fill((5, 5, 5))
+        arr.close()
+        self.assertEqual(arr.shown_frame(), [(100, 100, 100)] * NUMBER_LIGHTS)
+
+    def test_shown_frame_has_white_on_rgbw_strips(self):
+        arr = light_arrangements_python.init_headless(
+            2, "./tests/positions2d.csv", 10, rgbw=True
+        )
+        arr.fill((1, 2, 3, 4))
+        arr.show()
+        self.assertEqual(arr.shown_frame(), [(1, 2, 3, 4)] * NUMBER_LIGHTS)
+        arr.close()
+        self.assertEqual(arr.shown_frame(), [(0, 0, 0, 0)] * NUMBER_LIGHTS)
+
+    def test_bad_shutdown_policy(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_shutdown_policy("off")
+        with self.assertRaises(TypeError):
+            arr.set_shutdown_policy(1.5)
+
+
+class Timeout(unittest.TestCase):
+    def test_timeout_setting(self):
+        arr = get_light_arrangements()
+        self.assertIsNone(arr.timeout)
+        arr.timeout = 0.5
+        self.assertEqual(arr.timeout, 0.5)
+        arr.fill((1, 1, 1))
+        arr.timeout = None
+        self.assertIsNone(arr.timeout)
+
+    def test_bad_timeout(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.timeout = -1
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.timeout = 0
+
+    def test_timeout_poisons_arrangement(self):
+        arr = get_light_arrangements()
+        arr.timeout = 1e-9
+        with self.assertRaises(light_arrangements_python.ResponseTimeoutError):
+            for _ in range(1000):
+                arr.get_all()
+        self.assertTrue(arr.is_poisoned)
+        with self.assertRaises(light_arrangements_python.WorkerThreadError):
+            arr.get_by_index(0)
+        arr.close()
+        self.assertTrue(arr.is_closed)
+
+
+class Restart(unittest.TestCase):
+    def test_is_alive(self):
+        arr = get_light_arrangements()
+        self.assertTrue(arr.is_alive())
+        arr.close()
+        self.assertFalse(arr.is_alive())
+
+    def test_restart_clears_lights(self):
+        arr = get_light_arrangements()
+        arr.fill((1, 2, 3))
+        arr.restart()
+        self.assertTrue(arr.is_alive())
+        self.assertEqual(arr.get_by_index(0), (0, 0, 0))
+
+    def test_restart_after_close_raises(self):
+        arr = get_light_arrangements()
+        arr.close()
+        with self.assertRaises(light_arrangements_python.ArrangementClosedError):
+            arr.restart()
+        self.assertTrue(arr.is_closed)
+        self.assertFalse(arr.is_alive())
+
+    def test_restart_after_timeout(self):
+        arr = get_light_arrangements()
+        arr.timeout = 1e-9
+        with self.assertRaises(light_arrangements_python.ResponseTimeoutError):
+            for _ in range(1000):
+                arr.get_all()
+        self.assertFalse(arr.is_alive())
+        arr.timeout = None
+        arr.restart()
+        self.assertFalse(arr.is_poisoned)
+        self.assertEqual(arr.number_lights(), NUMBER_LIGHTS)
+
+
+def can_panic_worker():
+    return hasattr(light_arrangements_python.PyLightArrangement, "_panic_worker")
+
+
+class WorkerPanics(unittest.TestCase):
+    def test_no_worker_error_by_default(self):
+        arr = get_light_arrangements()
+        self.assertIsNone(arr.last_worker_error)
+
+    @unittest.skipUnless(can_panic_worker(), "Needs a debug build")
+    def test_panic_raises_and_is_kept(self):
+        arr = get_light_arrangements()
+        self.assertIsNone(arr.last_worker_error)
+        with self.assertRaises(light_arrangements_python.WorkerThreadError):
+            arr._panic_worker()
+        self.assertIsInstance(arr.last_worker_error, str)
+
+    @unittest.skipUnless(can_panic_worker(), "Needs a debug build")
+    def test_arrangement_works_after_panic(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.WorkerThreadError):
+            arr._panic_worker()
+        arr.set_by_index(0, (4, 5, 6))
+        self.assertEqual(arr.get_by_index(0), (4, 5, 6))
+
+    def test_short_color_does_not_reach_worker(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.InvalidColorError):
+            arr.fill([1])
+        self.assertIsNone(arr.last_worker_error)
+        self.assertTrue(arr.is_alive())
+
+
+class Threads(unittest.TestCase):
+    def test_calls_from_many_threads(self):
+        arr = get_light_arrangements()
+        failures = []
+
+        def draw(index):
+            color = (index, index, index)
+            for _ in range(50):
+                arr.set_by_index(index, color)
+                if arr.get_by_index(index) != color:
+                    failures.append(index)
+                arr.show()
+
+        threads = [threading.Thread(target=draw, args=(i,)) for i in range(8)]
+        for thread in threads:
+            thread.start()
+        for thread in threads:
+            thread.join()
+        self.assertEqual(failures, [])
+
+    def test_errors_only_reach_their_caller(self):
+        arr = get_light_arrangements()
+        failures = []
+
+        def read(index):
+            for _ in range(50):
+                try:
+                    arr.get_by_index(index)
+                except light_arrangements_python.IndexOutOfRangeError:
+                    if index < arr.number_lights():
+                        failures.append(index)
+                else:
+                    if index >= arr.number_lights():
+                        failures.append(index)
+
+        indices = [0, 1, 1000, 2000]
+        threads = [threading.Thread(target=read, args=(i,)) for i in indices]
+        for thread in threads:
+            thread.start()
+        for thread in threads:
+            thread.join()
+        self.assertEqual(failures, [])
+
+
+class Logging(unittest.TestCase):
+    def test_lifecycle_messages_logged_at_debug(self):
+        arr = get_light_arrangements()
+        with self.assertLogs("light_arrangements_python", level="DEBUG") as logs:
+            light_arrangements_python.reset_logging()
+            arr.close()
+        self.assertTrue(
+            any("Exiting LightArrangement thread" in line for line in logs.output)
+        )
+        self.assertTrue(all(line.startswith("DEBUG:") for line in logs.output))
+
+
+class Exceptions(unittest.TestCase):
+    def test_exceptions_subclass_value_error(self):
+        self.assertTrue(
+            issubclass(light_arrangements_python.LightArrangementError, ValueError)
+        )
+        for exception in [
+            light_arrangements_python.ArrangementInitError,
+            light_arrangements_python.IndexOutOfRangeError,
+            light_arrangements_python.DimensionMismatchError,
+            light_arrangements_python.InvalidColorError,
+            light_arrangements_python.WorkerThreadError,
+            light_arrangements_python.ResponseTimeoutError,
+            light_arrangements_python.ArrangementClosedError,
+        ]:
+            self.assertTrue(
+                issubclass(exception, light_arrangements_python.LightArrangementError)
+            )
+        self.assertTrue(
+            issubclass(
+                light_arrangements_python.ResponseTimeoutError,
+                light_arrangements_python.WorkerThreadError,
+            )
+        )
+
+    def test_bad_csv_raises_init_error(self):
+        with self.assertRaises(light_arrangements_python.ArrangementInitError) as raised:
+            light_arrangements_python.init_headless(2, "./tests/does_not_exist.csv", 10)
+        self.assertRegex(str(raised.exception), "csv file: .+")
+
+    def test_bad_index_raises_index_error(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.IndexOutOfRangeError):
+            arr.get_by_index(NUMBER_LIGHTS)
+
+    def test_bad_dimension_raises_dimension_error(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.DimensionMismatchError):
+            arr.set_closest([0.1, 0.2, 0.3], 0.2, (100, 100, 100))
+
+
 class Show(unittest.TestCase):
     def test_show(self):
         arr = get_light_arrangements()
         arr.show()
 
 
+class Brightness(unittest.TestCase):
+    def test_brightness_setting(self):
+        arr = get_light_arrangements()
+        self.assertEqual(arr.brightness, 1.0)
+        arr.set_brightness(0.25)
+        self.assertEqual(arr.brightness, 0.25)
+
+    def test_bad_brightness(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_brightness(1.5)
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_brightness(-0.1)
+        self.assertEqual(arr.brightness, 1.0)
+
+    def test_brightness_keeps_colors(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(0, (200, 100, 50))
+        arr.set_brightness(0.5)
+        arr.show()
+        self.assertEqual(arr.get_by_index(0), (200, 100, 50))
+
+    def test_brightness_dims_shown_frame(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(0, (200, 100, 50))
+        arr.set_brightness(0.5)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (100, 50, 25))
+        arr.set_brightness(0.0)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (0, 0, 0))
+
+    def test_brightness_survives_restart(self):
+        arr = get_light_arrangements()
+        arr.set_brightness(0.5)
+        arr.restart()
+        self.assertEqual(arr.brightness, 0.5)
+        arr.fill((200, 200, 200))
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (100, 100, 100))
+
+
+class OutputCorrection(unittest.TestCase):
+    def test_gamma_setting(self):
+        arr = get_light_arrangements()
+        self.assertEqual(arr.gamma, 1.0)
+        arr.set_gamma(2.2)
+        self.assertEqual(arr.gamma, 2.2)
+
+    def test_bad_gamma(self):
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_gamma(0)
+
+    def test_lookup_tables(self):
+        arr = get_light_arrangements()
+        table = [value // 2 for value in range(256)]
+        arr.set_lookup_tables(table, table, table)
+        self.assertIsNone(arr.gamma)
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_lookup_tables(table[:100], table, table)
+
+    def test_white_balance(self):
+        arr = get_light_arrangements()
+        self.assertEqual(arr.white_balance, (1.0, 1.0, 1.0))
+        arr.set_white_balance(1.0, 0.9, 0.7)
+        self.assertEqual(arr.white_balance, (1.0, 0.9, 0.7))
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_white_balance(1.0, 1.0, 2.0)
+
+    def test_gamma_corrects_shown_frame(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(0, (200, 100, 50))
+        arr.set_gamma(1.0)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (200, 100, 50))
+        arr.set_gamma(2.0)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (157, 39, 10))
+
+    def test_lookup_tables_map_shown_frame(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(0, (200, 100, 50))
+        identity = list(range(256))
+        arr.set_lookup_tables(identity, identity, identity)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (200, 100, 50))
+        half = [value // 2 for value in range(256)]
+        arr.set_lookup_tables(half, identity, [0] * 256)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (100, 100, 0))
+
+    def test_white_lookup_table_on_rgbw_strips(self):
+        arr = light_arrangements_python.init_headless(
+            2, "./tests/positions2d.csv", 10, rgbw=True
+        )
+        arr.set_by_index(0, (200, 100, 50, 80))
+        identity = list(range(256))
+        arr.set_lookup_tables(identity, identity, identity)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (200, 100, 50, 80))
+        half = [value // 2 for value in range(256)]
+        arr.set_lookup_tables(identity, identity, identity, half)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (200, 100, 50, 40))
+
+    def test_white_balance_scales_shown_frame(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(0, (200, 100, 40))
+        arr.set_white_balance(1.0, 0.5, 0.25)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (200, 50, 10))
+
+    def test_lookup_applied_before_white_balance(self):
+        arr = get_light_arrangements()
+        arr.set_by_index(0, (200, 100, 40))
+        half = [value // 2 for value in range(256)]
+        arr.set_lookup_tables(half, half, half)
+        arr.set_white_balance(1.0, 0.5, 0.25)
+        arr.show()
+        self.assertEqual(arr.shown_frame()[0], (100, 25, 5))
+
+    def test_correction_keeps_colors(self):
+        arr = get_light_arrangements()
+        arr.set_gamma(2.8)
+        arr.set_white_balance(1.0, 0.9, 0.7)
+        arr.set_by_index(0, (200, 100, 50))
+        arr.show()
+        self.assertEqual(arr.get_by_index(0), (200, 100, 50))
+
+
+class PowerLimit(unittest.TestCase):
+    def test_estimated_power(self):
+        arr = get_light_arrangements()
+        off_power = arr.estimated_power()
+        arr.fill((255, 255, 255))
+        self.assertGreater(arr.estimated_power(), off_power)
+        self.assertIsNone(arr.max_milliamps)
+        self.assertEqual(arr.volts, 5.0)
+
+    def test_power_limit(self):
+        arr = light_arrangements_python.init_headless(
+            2, "./tests/positions2d.csv", 10, max_milliamps=500
+        )
+        self.assertEqual(arr.max_milliamps, 500)
+        arr.fill((255, 255, 255))
+        self.assertLessEqual(arr.estimated_power(), 0.5 * 5.0)
+        arr.show()
+        self.assertEqual(arr.get_by_index(0), (255, 255, 255))
+
+        # Each light draws 1mA, plus 20mA per channel at full brightness
+        shown = arr.shown_frame()
+        self.assertEqual(len(set(shown)), 1)
+        self.assertLess(shown[0][0], 255)
+        self.assertGreater(shown[0][0], 0)
+        milliamps = sum(1 + sum(values) / 255 * 20 for values in shown)
+        self.assertLessEqual(milliamps, 500)
+
+    def test_power_limit_follows_brightness(self):
+        arr = get_light_arrangements()
+        arr.fill((255, 255, 255))
+        full_power = arr.estimated_power()
+        arr.set_brightness(0.5)
+        self.assertLess(arr.estimated_power(), full_power)
+
+    @unittest.skipUnless(on_raspberry_pi(), "ws281x strips need a Raspberry Pi")
+    def test_estimate_follows_strip_brightness(self):
+        def full_power(brightness):
+            arr = light_arrangements_python.init_ws281x(
+                2, "./tests/positions2d.csv", 10, 100, 18, brightness, "rgb", 800000
+            )
+            arr.fill((255, 255, 255))
+            power = arr.estimated_power()
+            arr.close()
+            return power
+
+        idle_power = NUMBER_LIGHTS * 0.001 * 5.0
+        full = full_power(255) - idle_power
+        self.assertAlmostEqual(full_power(128) - idle_power, full * 128 / 255)
+
+    def test_remove_power_limit(self):
+        arr = get_light_arrangements()
+        arr.fill((255, 255, 255))
+        full_power = arr.estimated_power()
+        arr.set_power_limit(500, 12.0)
+        self.assertEqual(arr.volts, 12.0)
+        self.assertLessEqual(arr.estimated_power(), 0.5 * 12.0)
+        arr.set_power_limit(None)
+        self.assertEqual(arr.estimated_power(), full_power)
+
+    def test_bad_power_limit(self):
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            light_arrangements_python.init_headless(
+                2, "./tests/positions2d.csv", 10, max_milliamps=-1
+            )
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            light_arrangements_python.init_headless(
+                2, "./tests/positions2d.csv", 10, volts=0
+            )
+        arr = get_light_arrangements()
+        with self.assertRaises(light_arrangements_python.LightArrangementError):
+            arr.set_power_limit(500, 0)
+
+
 class NumberLights(unittest.TestCase):
     def test_number(self):
         arr = get_light_arrangements()